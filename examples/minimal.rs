@@ -0,0 +1,20 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Launches the watch TUI programmatically instead of through the CLI.
+//!
+//! ```shell
+//! XLSYNTH_TOOLS=$HOME/opt/xlsynth/latest cargo run --example minimal -- /tmp/my_file.x
+//! ```
+
+use dslx_watch_tui::{Config, Theme, XlsynthToolRunner};
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let file = std::env::args().nth(1).expect("usage: minimal <file.x>");
+
+    let mut config = Config::new(file);
+    config.theme = Theme::ansi16();
+    config.large_file_warn_kb = 256;
+
+    let tools = std::env::var("XLSYNTH_TOOLS")?;
+    dslx_watch_tui::run_tui_with_runner(config, Box::new(XlsynthToolRunner::new(tools)))
+}
@@ -0,0 +1,3107 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Application state and the DSLX -> IR -> delay info build pipeline.
+
+use crate::clipboard::{clipboard_available, copy_to_clipboard};
+use crate::config::{Config, DEFAULT_TIME_FORMAT};
+use crate::delay::{delay_csv, delay_json, parse_delay_info, parse_delay_summary, DelaySummary};
+use crate::diff::{diff_lines, unified_diff_lines, DiffLine, LineDiff};
+use crate::imports::imported_files;
+use crate::ir::{
+    call_graph_summary, display_name, fold_functions, function_at_line, ir_changelog, ir_dot,
+    ir_nodes, ir_signatures, op_stats, stats_summary, verification_summary, OpStats,
+};
+use crate::reduce::{reduce_lines, run_until};
+use crate::report;
+use crate::schedule::{parse_schedule, PipelineSchedule};
+use crate::search::Search;
+use crate::state::SavedState;
+use crate::theme::Theme;
+use crate::tools::{missing_tools, SharedToolRunner, ToolRunner, CODEGEN_TOOL, EQUIVALENCE_TOOL};
+use crate::watch::WatcherKind;
+use ratatui::layout::Rect;
+use regex::Regex;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+use std::{fs, io};
+
+/// Most navigation states kept for Ctrl+Z.
+const NAV_UNDO_LIMIT: usize = 50;
+
+/// A snapshot of where the user was looking, for navigation undo/redo.
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) struct NavState {
+    selected_entry: usize,
+    selected_tab: usize,
+    results_scroll: usize,
+}
+
+/// Upper bound on the time `:reduce` spends shrinking a failing file.
+const REDUCE_TIME_LIMIT: Duration = Duration::from_secs(30);
+
+/// Lines of a successful stage's stderr kept as warnings; the diagnostics
+/// view has the rest.
+const STDERR_WARNING_LINES: usize = 20;
+
+/// Bytes of each stream kept per stage for the diagnostics view.
+const STAGE_OUTPUT_LIMIT: usize = 64 * 1024;
+
+/// A tool invocation from the last build, kept verbatim for diagnostics.
+#[derive(Clone)]
+struct StageOutput {
+    tool: String,
+    args: Vec<String>,
+    status: String,
+    stdout: String,
+    stderr: String,
+    // Wall-clock run time; `None` if the tool could not be started.
+    elapsed: Option<Duration>,
+}
+
+/// Delay models `D` cycles through, in order, unless `--delay-models` lists
+/// others.
+pub(crate) const DELAY_MODELS: [&str; 3] = ["asap7", "sky130", "unit"];
+
+/// Stage name and default label of each results tab, indexed by
+/// `selected_tab`.
+pub(crate) const TABS: [(&str, &str); 10] = [
+    ("unopt", "unopt IR"),
+    ("opt", "opt IR"),
+    ("delay", "delay info"),
+    ("callgraph", "call graph"),
+    ("log", "log"),
+    ("stats", "stats"),
+    ("verify", "verify"),
+    ("verilog", "Verilog"),
+    ("optdiff", "opt diff"),
+    ("equiv", "equivalence"),
+];
+
+/// Everything a build produces for the results and error panes, kept aside
+/// while the display shows an older build in manual apply mode.
+#[derive(Clone, Default)]
+struct BuildResults {
+    unopt_ir: String,
+    opt_ir: String,
+    delay_info: String,
+    delay_summary: Option<DelaySummary>,
+    opt_stats: Option<OpStats>,
+    optimizer_diff: OptimizerDiff,
+    prev_delay_info: String,
+    delay_diff: LineDiff,
+    prev_opt_ir: String,
+    opt_ir_diff: LineDiff,
+    compare_opt_ir: String,
+    verilog: String,
+    pipeline_schedule: Option<PipelineSchedule>,
+    equivalence: Equivalence,
+    error_message: Option<String>,
+    error_span: Option<(usize, usize)>,
+    stage_errors: Vec<(String, String)>,
+    selected_error: usize,
+    warnings: Vec<String>,
+    build_status: Option<BuildStatus>,
+    interpreter_ok: Option<bool>,
+    test_output: Option<String>,
+    stage_outputs: Vec<StageOutput>,
+    hook_log: String,
+}
+
+/// A watched file's source, results and position while another file is
+/// active.
+#[derive(Default)]
+struct FileState {
+    file_path: Option<String>,
+    code: String,
+    results: BuildResults,
+    staged_results: Option<BuildResults>,
+    entry_points: Vec<String>,
+    entry_signatures: Vec<String>,
+    selected_entry: usize,
+    entry_status: HashMap<String, bool>,
+    compare_entry: Option<String>,
+    test_filter: Option<String>,
+    input_warning: Option<String>,
+    last_update: Option<chrono::DateTime<chrono::Local>>,
+    last_code_hash: Option<u64>,
+    file_empty: bool,
+    source_cursor_row: usize,
+    source_scroll: usize,
+    last_change_at: Option<Instant>,
+    refreshed_at: Option<Instant>,
+    last_build_at: Option<Instant>,
+    last_build_duration: Duration,
+    // Changed on disk, or never built, since it was last active; it is
+    // rebuilt once it becomes active again.
+    changed: bool,
+}
+
+/// What to do with a build's results once it finishes.
+#[derive(Clone, Copy, PartialEq)]
+enum BuildPurpose {
+    /// Show them.
+    Show,
+    /// Show them and announce how the opt IR changed, after a file change.
+    Announce,
+    /// Keep them for `a` to show, after a file change in manual apply mode.
+    Stage,
+}
+
+/// What a build copy runs.
+#[derive(Clone, Copy, PartialEq)]
+enum BuildJob {
+    /// The whole pipeline.
+    Full,
+    /// Delay analysis and codegen against the opt IR the last build wrote,
+    /// after the delay model changes.
+    Delay,
+    /// Codegen alone against that opt IR, after the pipeline stage count
+    /// changes.
+    Codegen,
+    /// The comparison entry's opt IR from the unopt IR the last build wrote,
+    /// after an entry is pinned.
+    CompareOpt,
+    /// The equivalence check of the IR the last build wrote, when the
+    /// equivalence tab is shown or `:equiv` asks for it.
+    Equivalence,
+}
+
+impl BuildJob {
+    /// The tools whose outputs from the last build a rerun replaces.
+    fn tools(self) -> &'static [&'static str] {
+        match self {
+            // The comparison's `opt_main` run is not told apart from the
+            // build's own, so the build's outputs are kept as they are.
+            BuildJob::Full | BuildJob::CompareOpt => &[],
+            BuildJob::Delay => &["delay_info_main", CODEGEN_TOOL],
+            BuildJob::Codegen => &[CODEGEN_TOOL],
+            BuildJob::Equivalence => &[EQUIVALENCE_TOOL],
+        }
+    }
+}
+
+/// The newest build handed to the build worker.
+struct BackgroundBuild {
+    generation: u64,
+    purpose: BuildPurpose,
+    job: BuildJob,
+    started_at: Instant,
+}
+
+/// A `:reduce` running on a background thread.
+struct Reduction {
+    stage: String,
+    // Set to stop the reduction before its next candidate.
+    cancel: Arc<AtomicBool>,
+    progress: Receiver<ReduceProgress>,
+}
+
+/// What a background reduction reports.
+enum ReduceProgress {
+    // Candidates tried so far and the lines of the smallest reproducer.
+    Tried { candidates: usize, lines: usize },
+    // The reduced source, or `None` if the failure did not reproduce.
+    Done(Option<String>),
+}
+
+/// Channels to the thread that runs background builds one at a time. Each
+/// build is a copy of the `App`, sent back once it has run.
+struct BuildWorker {
+    jobs: Sender<App>,
+    results: Receiver<App>,
+}
+
+impl BuildWorker {
+    fn start() -> Self {
+        let (jobs, queued) = mpsc::channel();
+        let (finished, results) = mpsc::channel();
+        std::thread::spawn(move || run_build_worker(queued, finished));
+        Self { jobs, results }
+    }
+}
+
+/// Frames of the status-line spinner shown while building.
+const SPINNER: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
+/// Heads the artifacts we write, with a hash of the rest of the file, so a
+/// later session overwrites them without a backup unless they were edited.
+const GENERATED_MARKER: &str = "Generated by dslx-playground";
+
+/// Which pane the arrow keys act on.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Focus {
+    /// Arrow keys change the selected entry point.
+    Entries,
+    /// Arrow keys scroll the results pane.
+    Results,
+}
+
+/// Where the mouse-aware parts of the layout were at the last draw.
+#[derive(Default)]
+pub(crate) struct MouseAreas {
+    // Each tab title's cells on the tab row, with its tab.
+    pub(crate) tabs: Vec<(Rect, usize)>,
+    // Rows of the entry list, and the entry shown on the first of them.
+    pub(crate) entries: Rect,
+    pub(crate) first_entry: usize,
+    pub(crate) results: Rect,
+}
+
+/// State of the watch session: the source, its generated artifacts and the
+/// view state of the TUI.
+pub struct App {
+    pub(crate) code: String,
+    pub(crate) unopt_ir: String,
+    pub(crate) opt_ir: String,
+    pub(crate) delay_info: String,
+    // Critical path summary of `delay_info`, if it could be parsed.
+    pub(crate) delay_summary: Option<DelaySummary>,
+    // Node count and op tally of the selected entry in `opt_ir`.
+    pub(crate) opt_stats: Option<OpStats>,
+    // The unopt IR diffed against `opt_ir`, for the opt diff tab.
+    pub(crate) optimizer_diff: OptimizerDiff,
+    pub(crate) error_message: Option<String>,
+    // 1-based line and column of the source location an IR conversion error
+    // points at, highlighted in the code pane.
+    pub(crate) error_span: Option<(usize, usize)>,
+    // Index into `TABS`: 0 unopt IR, 1 opt IR, 2 delay info, 3 call graph,
+    // 4 log, 5 stats, 6 verify, 7 Verilog, 8 opt diff, 9 equivalence.
+    pub(crate) selected_tab: usize,
+    // Tabs shown, in order, as `selected_tab` indices with their labels.
+    pub(crate) tab_order: Vec<(usize, String)>,
+    pub(crate) dslx_stdlib_path: Option<String>,
+    // How far the last build got; `None` before the first build.
+    pub(crate) build_status: Option<BuildStatus>,
+    pub(crate) test_output: Option<String>,
+    pub(crate) entry_points: Vec<String>,
+    pub(crate) selected_entry: usize,
+    pub(crate) file_path: Option<String>,
+    pub(crate) last_update: Option<chrono::DateTime<chrono::Local>>,
+    pub(crate) time_format: String,
+    pub(crate) relative_time: bool,
+    // Artifact paths written by this session; anything else found on disk is
+    // treated as user-owned and backed up before we overwrite it.
+    pub(crate) written_artifacts: HashSet<PathBuf>,
+    pub(crate) warnings: Vec<String>,
+    // Highlighted row in the source panel; purely navigational.
+    pub(crate) source_cursor_row: usize,
+    pub(crate) source_scroll: usize,
+    // Context lines kept around the cursor line when scrolling to it.
+    pub(crate) scroll_off: usize,
+    // Digits typed ahead of a command such as `Ctrl+G`.
+    pub(crate) count_prefix: Option<usize>,
+    // First visible line of the results pane.
+    pub(crate) results_scroll: usize,
+    // Scroll position of each tab while another one is selected.
+    tab_scrolls: [usize; TABS.len()],
+    // Column offset of each tab while another one is selected.
+    tab_hscrolls: [usize; TABS.len()],
+    // Hashes of the unopt IR, opt IR and delay info their scroll positions
+    // refer to; a tab whose content is replaced scrolls back to the top.
+    scrolled_content: [u64; 3],
+    // Highlighted line of the results pane while it has focus; kept within
+    // the visible window at draw time.
+    pub(crate) results_cursor: usize,
+    // Visible height of the results pane at the last draw.
+    pub(crate) results_viewport: usize,
+    // Layout of the last draw, which mouse events are mapped against.
+    pub(crate) mouse_areas: MouseAreas,
+    // When set, an interpreter/JIT comparison mismatch is reported as a warning
+    // instead of failing the build.
+    pub(crate) compare_mismatch_non_fatal: bool,
+    // Delay info from the previous successful build, for the delay diff view.
+    pub(crate) prev_delay_info: String,
+    // `prev_delay_info` diffed against `delay_info`, for the delay diff view.
+    pub(crate) delay_diff: LineDiff,
+    pub(crate) show_delay_diff: bool,
+    // `--delay_model` passed to `delay_info_main`.
+    pub(crate) delay_model: String,
+    // Models `D` cycles through.
+    pub(crate) delay_models: Vec<String>,
+    // Shown in the file pane title, e.g. when the input is not a `.x` file.
+    pub(crate) input_warning: Option<String>,
+    // Set when native file watching appears not to deliver changes.
+    pub(crate) watch_warning: Option<String>,
+    pub(crate) theme: Theme,
+    // While paused, file changes are only counted and shown in the source
+    // pane; resuming rebuilds once.
+    pub(crate) paused: bool,
+    pub(crate) pending_changes: usize,
+    pub(crate) watcher_kind: WatcherKind,
+    // Result of the interpreter/JIT-compare stage alone; `None` when it did
+    // not run (an earlier stage failed or the binary is absent).
+    pub(crate) interpreter_ok: Option<bool>,
+    // Sources above this size get a "large file" badge in the status bar.
+    pub(crate) large_file_warn_bytes: usize,
+    // Whether the most recent build with each entry as top succeeded.
+    pub(crate) entry_status: HashMap<String, bool>,
+    // Text typed after `:`; `Some` while the command line is open.
+    pub(crate) command_input: Option<String>,
+    // Short-lived message shown in place of the hint bar.
+    pub(crate) toast: Option<(String, std::time::Instant)>,
+    // When a change to the file was last seen and when the last build
+    // finished and took.
+    pub(crate) last_change_at: Option<std::time::Instant>,
+    // When a rebuild was last requested with `r`.
+    refreshed_at: Option<std::time::Instant>,
+    last_build_at: Option<std::time::Instant>,
+    last_build_duration: Duration,
+    // Set by the TUI so builds run on a worker thread while the UI keeps
+    // drawing; otherwise they run inline.
+    pub(crate) background_builds: bool,
+    build: Option<BackgroundBuild>,
+    build_worker: Option<BuildWorker>,
+    // Generation of the newest build requested; shared with build copies,
+    // which stop running tools once it moves past their `build_id`.
+    build_generation: Arc<AtomicU64>,
+    build_id: Option<u64>,
+    // What this build copy runs; always `Full` for the displayed `App`.
+    job: BuildJob,
+    // User navigation history; rebuilds never push here.
+    pub(crate) nav_undo_stack: Vec<NavState>,
+    pub(crate) nav_redo_stack: Vec<NavState>,
+    // Optional manifest of entry names that replaces regex discovery.
+    pub(crate) tops_file: Option<PathBuf>,
+    // Entry selected by name on every build (`--top`), until one is chosen
+    // by hand.
+    pub(crate) pinned_top: Option<String>,
+    // The source is empty or whitespace-only, so no build was attempted.
+    pub(crate) file_empty: bool,
+    // "(params) -> ret" for each entry point, index-aligned with
+    // `entry_points`; empty when the IR has no such function.
+    pub(crate) entry_signatures: Vec<String>,
+    // IR node whose fan-in/fan-out is shown beside the results pane.
+    pub(crate) inspected_node: Option<String>,
+    // Whether the interpreter also compares its results against the JIT.
+    pub(crate) jit_compare: bool,
+    // Shell commands run around each build, and their combined output.
+    pre_build_hook: Option<String>,
+    post_build_hook: Option<String>,
+    pub(crate) hook_log: String,
+    // Command started in the background after each successful build, the
+    // channel its exit code and output arrive on, and its last known state.
+    on_build: Option<String>,
+    on_build_rx: Option<Receiver<(Option<i32>, String)>>,
+    pub(crate) on_build_status: Option<OnBuildStatus>,
+    // The `:reduce` under way, if any.
+    reduction: Option<Reduction>,
+    // A `:equiv` waiting for the build under way to finish.
+    equivalence_requested: bool,
+    // Search over the selected tab's text, started with `/`.
+    pub(crate) search: Option<Search>,
+    // FNV-1a hash of the source the last build ran on.
+    last_code_hash: Option<u64>,
+    pub(crate) focus: Focus,
+    // Column offset of the results pane when scrolled horizontally.
+    pub(crate) results_hscroll: usize,
+    pub(crate) max_width: Option<u16>,
+    // Raw output of each stage of the last build, and whether the
+    // diagnostics view showing it replaces the selected tab.
+    stage_outputs: Vec<StageOutput>,
+    pub(crate) show_diagnostics: bool,
+    // Results scroll positions saved with `m<digit>`, keyed by tab and
+    // digit, with a hash of the tab text they were set on.
+    bookmarks: HashMap<(usize, char), (u64, usize)>,
+    // `m` or `'` waiting for the bookmark digit.
+    pub(crate) pending_bookmark: Option<char>,
+    // With inline diffs on, the opt IR pane briefly marks what the last
+    // rebuild changed against `prev_opt_ir`, as `opt_ir_diff`, from
+    // `opt_ir_changed_at` until the next key press or timeout.
+    pub(crate) inline_opt_diff: bool,
+    pub(crate) prev_opt_ir: String,
+    pub(crate) opt_ir_diff: LineDiff,
+    pub(crate) opt_ir_changed_at: Option<std::time::Instant>,
+    // Retention limits for the `.bak` backups `write_artifact` makes.
+    backup_max_entries: Option<usize>,
+    backup_max_age: Option<Duration>,
+    // Entry whose opt IR is shown beside the selected entry's, and that IR
+    // (or the reason it could not be built) from the last build.
+    pub(crate) compare_entry: Option<String>,
+    pub(crate) compare_opt_ir: String,
+    // Every watched file in `--file` order. The active file's slot is empty
+    // while its state lives in the fields of `App`.
+    files: Vec<Option<FileState>>,
+    pub(crate) active_file: usize,
+    // Output of `codegen_main` for the opt IR, or why there is none.
+    pub(crate) verilog: String,
+    // `--pipeline_stages` and `--clock_period_ps` for codegen; a single
+    // stage when neither is set.
+    pub(crate) pipeline_stages: Option<usize>,
+    pub(crate) clock_period_ps: Option<u64>,
+    // Per-stage breakdown of the schedule codegen chose for the Verilog.
+    pub(crate) pipeline_schedule: Option<PipelineSchedule>,
+    pub(crate) equivalence: Equivalence,
+    // With manual apply, builds after a file change are kept here until
+    // `apply_staged_results` swaps them into view.
+    pub(crate) manual_apply: bool,
+    staged_results: Option<BuildResults>,
+    // Whether results lines too long to render are shown in full rather
+    // than with their middle elided.
+    pub(crate) expand_long_lines: bool,
+    // Whether results lines wrap instead of running off the right edge.
+    pub(crate) wrap_enabled: bool,
+    // Whether the source pane is collapsed so the results take the full
+    // width.
+    pub(crate) code_pane_hidden: bool,
+    // Whether results lines are numbered like the source pane's.
+    pub(crate) line_numbers: bool,
+    // Whether the keybinding overlay is open; it takes every key until
+    // closed.
+    pub(crate) show_help: bool,
+    // First overlay row shown when it is taller than the terminal, clamped
+    // at draw time, and the rows that fit, for paging.
+    pub(crate) help_scroll: usize,
+    pub(crate) help_viewport: usize,
+    // Whether the test output collapses passing tests to a count line.
+    pub(crate) failures_only: bool,
+    // IR functions shown collapsed to one line in the IR tabs.
+    folded_functions: HashSet<String>,
+    // The only `#[test]` the interpreter runs; all of them when `None`.
+    pub(crate) test_filter: Option<String>,
+    // Every failing stage of the last build with its error, and the one the
+    // error pane shows.
+    pub(crate) stage_errors: Vec<(String, String)>,
+    pub(crate) selected_error: usize,
+    tool_runner: SharedToolRunner,
+}
+
+impl App {
+    pub fn new(tool_runner: Box<dyn ToolRunner>) -> Self {
+        Self::with_runner(SharedToolRunner::new(tool_runner))
+    }
+
+    fn with_runner(tool_runner: SharedToolRunner) -> Self {
+        Self {
+            code: String::new(),
+            unopt_ir: String::new(),
+            opt_ir: String::new(),
+            delay_info: String::new(),
+            delay_summary: None,
+            opt_stats: None,
+            optimizer_diff: OptimizerDiff::default(),
+            error_message: None,
+            error_span: None,
+            selected_tab: 0,
+            tab_order: TABS
+                .iter()
+                .enumerate()
+                .map(|(i, (_, label))| (i, label.to_string()))
+                .collect(),
+            dslx_stdlib_path: None,
+            build_status: None,
+            test_output: None,
+            entry_points: Vec::new(),
+            selected_entry: 0,
+            file_path: None,
+            last_update: None,
+            time_format: String::from(DEFAULT_TIME_FORMAT),
+            relative_time: false,
+            written_artifacts: HashSet::new(),
+            warnings: Vec::new(),
+            source_cursor_row: 0,
+            source_scroll: 0,
+            scroll_off: 3,
+            count_prefix: None,
+            results_scroll: 0,
+            tab_scrolls: [0; TABS.len()],
+            tab_hscrolls: [0; TABS.len()],
+            scrolled_content: [0; 3],
+            results_cursor: 0,
+            results_viewport: 0,
+            mouse_areas: MouseAreas::default(),
+            compare_mismatch_non_fatal: false,
+            prev_delay_info: String::new(),
+            delay_diff: LineDiff::default(),
+            show_delay_diff: false,
+            delay_model: String::from(DELAY_MODELS[0]),
+            delay_models: DELAY_MODELS.map(String::from).to_vec(),
+            input_warning: None,
+            watch_warning: None,
+            theme: Theme::default_colors(),
+            paused: false,
+            pending_changes: 0,
+            watcher_kind: WatcherKind::Notify,
+            interpreter_ok: None,
+            large_file_warn_bytes: 100 * 1024,
+            entry_status: HashMap::new(),
+            command_input: None,
+            toast: None,
+            last_change_at: None,
+            refreshed_at: None,
+            last_build_at: None,
+            last_build_duration: Duration::ZERO,
+            background_builds: false,
+            build: None,
+            build_worker: None,
+            build_generation: Arc::new(AtomicU64::new(0)),
+            build_id: None,
+            job: BuildJob::Full,
+            nav_undo_stack: Vec::new(),
+            nav_redo_stack: Vec::new(),
+            tops_file: None,
+            pinned_top: None,
+            file_empty: false,
+            entry_signatures: Vec::new(),
+            inspected_node: None,
+            jit_compare: true,
+            pre_build_hook: None,
+            post_build_hook: None,
+            hook_log: String::new(),
+            on_build: None,
+            on_build_rx: None,
+            reduction: None,
+            equivalence_requested: false,
+            on_build_status: None,
+            search: None,
+            last_code_hash: None,
+            focus: Focus::Entries,
+            results_hscroll: 0,
+            max_width: None,
+            stage_outputs: Vec::new(),
+            show_diagnostics: false,
+            bookmarks: HashMap::new(),
+            pending_bookmark: None,
+            inline_opt_diff: false,
+            prev_opt_ir: String::new(),
+            opt_ir_diff: LineDiff::default(),
+            opt_ir_changed_at: None,
+            backup_max_entries: None,
+            backup_max_age: None,
+            compare_entry: None,
+            compare_opt_ir: String::new(),
+            verilog: String::new(),
+            pipeline_stages: None,
+            clock_period_ps: None,
+            pipeline_schedule: None,
+            equivalence: Equivalence::NotRun,
+            files: vec![None],
+            active_file: 0,
+            manual_apply: false,
+            staged_results: None,
+            expand_long_lines: false,
+            wrap_enabled: false,
+            line_numbers: false,
+            code_pane_hidden: false,
+            show_help: false,
+            help_scroll: 0,
+            help_viewport: 0,
+            failures_only: false,
+            folded_functions: HashSet::new(),
+            test_filter: None,
+            stage_errors: Vec::new(),
+            selected_error: 0,
+            tool_runner,
+        }
+    }
+
+    /// Creates an app for `config`, reading the source file up front so an
+    /// unreadable or non-UTF-8 input is reported before any UI starts.
+    pub fn from_config(config: &Config, tool_runner: Box<dyn ToolRunner>) -> Result<Self, String> {
+        let file_path = config.file.to_string_lossy().into_owned();
+        let mut app = App::new(tool_runner);
+        app.code = read_dslx_source(&file_path)?;
+        app.input_warning = extension_warning(&config.file);
+        for file in &config.extra_files {
+            let path = file.to_string_lossy().into_owned();
+            app.files.push(Some(FileState {
+                code: read_dslx_source(&path)?,
+                input_warning: extension_warning(file),
+                file_path: Some(path),
+                changed: true,
+                ..FileState::default()
+            }));
+        }
+        // Tools run in the working directory, not necessarily ours.
+        app.dslx_stdlib_path = config.dslx_stdlib_path.as_deref().map(absolute_path);
+        app.tops_file = config.tops_file.clone();
+        app.pinned_top = config.top.clone();
+        app.compare_mismatch_non_fatal = config.compare_mismatch_non_fatal;
+        app.jit_compare = !config.no_jit_compare;
+        app.pre_build_hook = config.pre_build_hook.clone();
+        app.post_build_hook = config.post_build_hook.clone();
+        app.on_build = config.on_build.clone();
+        app.large_file_warn_bytes = config.large_file_warn_kb * 1024;
+        app.theme = config.theme.clone();
+        app.focus = config.focus;
+        app.max_width = config.max_width;
+        app.backup_max_entries = config.backup_max_entries;
+        app.backup_max_age = config.backup_max_age;
+        app.test_filter = config.test_filter.clone();
+        app.time_format = config.time_format.clone();
+        app.relative_time = config.relative_time;
+        app.scroll_off = config.scroll_off;
+        app.manual_apply = config.manual_apply;
+        app.delay_model = config.delay_model.clone();
+        app.delay_models = config.delay_models.clone();
+        app.pipeline_stages = config.pipeline_stages;
+        app.clock_period_ps = config.clock_period_ps;
+        if let Some(tabs) = &config.tabs {
+            app.tab_order = tabs
+                .iter()
+                .map(
+                    |(stage, label)| match TABS.iter().position(|(name, _)| name == stage) {
+                        Some(i) => Ok((i, label.clone())),
+                        None => Err(format!("unknown tab stage '{}'", stage)),
+                    },
+                )
+                .collect::<Result<_, _>>()?;
+            app.selected_tab = app.tab_order.first().ok_or("no tabs configured")?.0;
+        }
+        if let Some(stage) = &config.tab {
+            app.selected_tab = app
+                .tab_order
+                .iter()
+                .find(|(tab, _)| TABS[*tab].0 == stage)
+                .ok_or_else(|| format!("tab '{}' is not shown", stage))?
+                .0;
+        }
+        // Sweep backups left behind by earlier sessions.
+        let extra_paths = config.extra_files.iter().map(|f| f.to_string_lossy());
+        for path in extra_paths.chain([file_path.as_str().into()]) {
+            for artifact in ["unopt.ir", "opt.ir"] {
+                app.prune_backups(Path::new(&format!("{}.{}", path, artifact)), None);
+            }
+        }
+        app.file_path = Some(file_path);
+        Ok(app)
+    }
+
+    /// The unoptimized IR from the most recent successful conversion.
+    pub fn unopt_ir(&self) -> &str {
+        &self.unopt_ir
+    }
+
+    /// The optimized IR for the selected entry point.
+    pub fn opt_ir(&self) -> &str {
+        &self.opt_ir
+    }
+
+    pub fn delay_info(&self) -> &str {
+        &self.delay_info
+    }
+
+    /// The failure from the most recent build, if any stage failed.
+    pub fn error_message(&self) -> Option<&str> {
+        self.error_message.as_deref()
+    }
+
+    pub fn entry_points(&self) -> &[String] {
+        &self.entry_points
+    }
+
+    /// Keeps the source cursor on an existing line, e.g. after the file shrinks.
+    pub(crate) fn clamp_source_cursor(&mut self) {
+        let line_count = self.code.lines().count();
+        self.source_cursor_row = self.source_cursor_row.min(line_count.saturating_sub(1));
+    }
+
+    /// Writes an artifact file, first moving any pre-existing file that was
+    /// produced neither by this session nor, unedited, by an earlier one out
+    /// of the way to a `.bak` name.
+    fn write_artifact(&mut self, path: &str, contents: &str) -> io::Result<()> {
+        let path = PathBuf::from(path);
+        let marker = artifact_marker(contents);
+        let header = artifact_header(&path, &marker);
+        if path.exists()
+            && !self.written_artifacts.contains(&path)
+            && !fs::read_to_string(&path)
+                .is_ok_and(|existing| is_generated_artifact(&path, &existing))
+        {
+            let backup = backup_path_for(&path);
+            fs::rename(&path, &backup)?;
+            // Renaming keeps the old mtime, but backups age from when they
+            // were made.
+            fs::File::options()
+                .write(true)
+                .open(&backup)?
+                .set_modified(SystemTime::now())?;
+            self.warnings.push(format!(
+                "{} already existed; moved it to {}",
+                path.display(),
+                backup.display()
+            ));
+            self.prune_backups(&path, Some(&backup));
+        }
+        match header {
+            Some(header) => fs::write(&path, format!("{}\n{}", header, contents))?,
+            None => {
+                fs::write(&path, contents)?;
+                fs::write(marker_path_for(&path), marker)?;
+            }
+        }
+        self.written_artifacts.insert(path);
+        Ok(())
+    }
+
+    /// Deletes the oldest `.bak` backups of `path` beyond the retention
+    /// limits, except `keep`, the backup just made. Removal failures are
+    /// ignored, since a concurrent session may be sweeping the same backups.
+    fn prune_backups(&self, path: &Path, keep: Option<&Path>) {
+        if self.backup_max_entries.is_none() && self.backup_max_age.is_none() {
+            return;
+        }
+        let (Some(dir), Some(name)) = (path.parent(), path.file_name()) else {
+            return;
+        };
+        let dir = if dir.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            dir
+        };
+        let backup_re = Regex::new(&format!(
+            r"^{}\.bak(\.\d+)?$",
+            regex::escape(&name.to_string_lossy())
+        ))
+        .unwrap();
+        let Ok(entries) = fs::read_dir(dir) else {
+            return;
+        };
+        let mut backups: Vec<(SystemTime, PathBuf)> = entries
+            .flatten()
+            .filter(|e| backup_re.is_match(&e.file_name().to_string_lossy()))
+            .filter_map(|e| Some((e.metadata().ok()?.modified().ok()?, e.path())))
+            .collect();
+        // Newest first, so everything past the limit is the oldest.
+        backups.sort_by_key(|b| std::cmp::Reverse(b.0));
+        let now = SystemTime::now();
+        for (i, (modified, backup)) in backups.iter().enumerate() {
+            let too_many = self.backup_max_entries.is_some_and(|max| i >= max);
+            let too_old = self
+                .backup_max_age
+                .is_some_and(|max_age| now.duration_since(*modified).unwrap_or_default() > max_age);
+            if (too_many || too_old) && keep != Some(backup.as_path()) {
+                let _ = fs::remove_file(backup);
+            }
+        }
+    }
+
+    /// Reads the `--tops-file` manifest, keeping only names defined in the
+    /// current IR and warning about the rest. Returns `None` when there is no
+    /// usable manifest so callers fall back to discovery.
+    fn read_tops_file(&mut self) -> Option<Vec<String>> {
+        let path = self.tops_file.as_ref()?;
+        let contents = fs::read_to_string(path).ok()?;
+        let defined_re = Regex::new(r"(?m)^(?:top )?fn (\w+)").unwrap();
+        let defined: HashSet<&str> = defined_re
+            .captures_iter(&self.unopt_ir)
+            .map(|cap| cap.get(1).unwrap().as_str())
+            .collect();
+        let mut tops = Vec::new();
+        let mut missing = Vec::new();
+        for name in contents
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        {
+            // Before the first conversion there is no IR to check against.
+            if self.unopt_ir.is_empty() || defined.contains(name) {
+                tops.push(name.to_string());
+            } else {
+                missing.push(name.to_string());
+            }
+        }
+        if !missing.is_empty() {
+            self.warnings.push(format!(
+                "tops file lists functions not in the IR: {}",
+                missing.join(", ")
+            ));
+        }
+        if tops.is_empty() {
+            None
+        } else {
+            Some(tops)
+        }
+    }
+
+    fn update_entry_points(&mut self) {
+        if let Some(tops) = self.read_tops_file() {
+            self.entry_points = tops;
+        } else {
+            // Use regex to extract function names from unopt_ir
+            let re = Regex::new(r"(?m)^fn (\w+)").unwrap();
+            let mut matches = Vec::new();
+            for cap in re.captures_iter(&self.unopt_ir) {
+                matches.push(cap[1].to_string());
+            }
+            if matches.is_empty() {
+                matches.push("main".into());
+            }
+            self.entry_points = matches;
+        }
+        // Until the first conversion the list is a placeholder, so an entry
+        // restored from the last session is kept until it can be checked.
+        if self.selected_entry >= self.entry_points.len() && !self.unopt_ir.is_empty() {
+            self.selected_entry = 0;
+        }
+
+        let signatures = ir_signatures(&self.unopt_ir);
+        self.entry_signatures = self
+            .entry_points
+            .iter()
+            .map(|ep| signatures.get(ep).cloned().unwrap_or_default())
+            .collect();
+    }
+
+    /// Runs a build, recording when it finished and how long it took.
+    fn run_conversion(&mut self) {
+        let start = std::time::Instant::now();
+        self.build_steps();
+        self.last_build_at = Some(std::time::Instant::now());
+        self.last_build_duration = start.elapsed();
+    }
+
+    /// Runs the pre-build hook, the tool pipeline and then the post-build
+    /// hook, which runs whether or not the build succeeded.
+    fn build_steps(&mut self) {
+        self.build_status = Some(BuildStatus::NotRun);
+        self.interpreter_ok = None;
+        self.warnings.clear();
+        self.hook_log.clear();
+        self.stage_outputs.clear();
+        self.stage_errors.clear();
+        self.selected_error = 0;
+        if let Some(hook) = self.pre_build_hook.clone() {
+            let (ok, output) = run_hook(&hook, &[]);
+            self.hook_log.push_str(&format!("$ {}\n{}", hook, output));
+            if !ok {
+                self.error_message = Some(format!("Pre-build hook failed: {}", output));
+                self.run_post_build_hook();
+                return;
+            }
+        }
+        self.run_pipeline();
+        if self.is_superseded() {
+            return;
+        }
+        self.run_post_build_hook();
+        if self.build_succeeded() {
+            self.start_on_build();
+        }
+    }
+
+    /// Starts the `--on-build` command on a background thread with the
+    /// source and artifact paths in `DSLX_FILE`, `UNOPT_IR` and `OPT_IR`. A
+    /// still-running earlier command is left to finish unobserved.
+    fn start_on_build(&mut self) {
+        let (Some(command), Some(file_path)) = (self.on_build.clone(), self.file_path.clone())
+        else {
+            return;
+        };
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            let result = match Command::new("sh")
+                .arg("-c")
+                .arg(&command)
+                .env("DSLX_FILE", &file_path)
+                .env("UNOPT_IR", format!("{}.unopt.ir", file_path))
+                .env("OPT_IR", format!("{}.opt.ir", file_path))
+                .output()
+            {
+                Ok(output) => (
+                    output.status.code(),
+                    format!(
+                        "$ {}\n{}{}",
+                        command,
+                        String::from_utf8_lossy(&output.stdout),
+                        String::from_utf8_lossy(&output.stderr)
+                    ),
+                ),
+                Err(e) => (
+                    None,
+                    format!("$ {}\nfailed to run command: {}\n", command, e),
+                ),
+            };
+            // The receiver is gone if a newer build replaced it.
+            let _ = tx.send(result);
+        });
+        self.on_build_rx = Some(rx);
+        self.on_build_status = Some(OnBuildStatus::Running);
+    }
+
+    /// Picks up the result of the `--on-build` command if it has finished,
+    /// adding its output to the log tab. With `wait`, blocks until it does.
+    pub(crate) fn poll_on_build(&mut self, wait: bool) {
+        let Some(rx) = &self.on_build_rx else {
+            return;
+        };
+        let result = if wait {
+            rx.recv().ok()
+        } else {
+            rx.try_recv().ok()
+        };
+        if let Some((code, output)) = result {
+            self.on_build_rx = None;
+            self.hook_log.push_str(&output);
+            if code != Some(0) {
+                self.show_toast(String::from("On-build command failed (see log tab)"));
+            }
+            self.on_build_status = Some(OnBuildStatus::Exited(code));
+        }
+    }
+
+    fn run_post_build_hook(&mut self) {
+        if let Some(hook) = self.post_build_hook.clone() {
+            let result = if self.build_succeeded() {
+                "pass"
+            } else {
+                "fail"
+            };
+            let (_, output) = run_hook(&hook, &[("BUILD_RESULT", result)]);
+            self.hook_log.push_str(&format!("$ {}\n{}", hook, output));
+        }
+    }
+
+    /// Runs one pipeline stage, keeping its raw output (or why it could not
+    /// be started) for the diagnostics view.
+    fn run_tool(&mut self, tool: &str, args: &[String]) -> io::Result<Output> {
+        let started = Instant::now();
+        let output = if self.is_superseded() {
+            Err(io::Error::new(
+                io::ErrorKind::Interrupted,
+                "superseded by a newer build",
+            ))
+        } else {
+            self.tool_runner.run(tool, args)
+        };
+        let elapsed = output.is_ok().then(|| started.elapsed());
+        if let Ok(output) = &output {
+            if output.status.success() {
+                self.push_stderr_warnings(tool, &output.stderr);
+            }
+        }
+        self.stage_outputs.push(StageOutput {
+            tool: tool.to_string(),
+            args: args.to_vec(),
+            status: match &output {
+                Ok(output) => output.status.to_string(),
+                Err(e) => format!("failed to start: {}", e),
+            },
+            stdout: output
+                .as_ref()
+                .map_or_else(|_| String::new(), |o| bounded_lossy(&o.stdout)),
+            stderr: output
+                .as_ref()
+                .map_or_else(|_| String::new(), |o| bounded_lossy(&o.stderr)),
+            elapsed,
+        });
+        output
+    }
+
+    /// Keeps what a tool that succeeded printed to stderr, such as
+    /// deprecation notices, as warnings of its own, one per line. The
+    /// interpreter is left out: its stderr is the test log.
+    fn push_stderr_warnings(&mut self, tool: &str, stderr: &[u8]) {
+        if tool == "dslx_interpreter_main" {
+            return;
+        }
+        let stderr = String::from_utf8_lossy(stderr);
+        let lines: Vec<&str> = stderr.lines().filter(|l| !l.trim().is_empty()).collect();
+        for line in lines.iter().take(STDERR_WARNING_LINES) {
+            self.warnings.push(format!("{}: {}", tool, line.trim_end()));
+        }
+        if lines.len() > STDERR_WARNING_LINES {
+            self.warnings.push(format!(
+                "{}: {} more lines (v shows them all)",
+                tool,
+                lines.len() - STDERR_WARNING_LINES
+            ));
+        }
+    }
+
+    /// Runs a stage whose failure to start fails the build, recording that
+    /// as the stage's error.
+    fn run_stage(&mut self, tool: &str, args: &[String]) -> Option<Output> {
+        match self.run_tool(tool, args) {
+            Ok(output) => Some(output),
+            Err(e) => {
+                let message = format!("Failed to run {}: {}", tool, e);
+                self.push_stage_error(tool, message.as_bytes());
+                None
+            }
+        }
+    }
+
+    /// Writes a stage's output to `path` for the next stage, recording a
+    /// failure as that stage's error.
+    fn write_stage_artifact(&mut self, tool: &str, path: &str, contents: &str) -> bool {
+        match self.write_artifact(path, contents) {
+            Ok(()) => true,
+            Err(e) => {
+                let message = format!("Failed to write {}: {}", path, e);
+                self.push_stage_error(tool, message.as_bytes());
+                false
+            }
+        }
+    }
+
+    /// How long each stage of the last build took, by short stage name, in
+    /// the order the stages ran.
+    pub fn stage_timings(&self) -> Vec<(String, Duration)> {
+        self.stage_outputs
+            .iter()
+            .filter_map(|stage| Some((stage_short_name(&stage.tool), stage.elapsed?)))
+            .collect()
+    }
+
+    /// The stage timings as one line, e.g. `irconv 120ms · opt 310ms`;
+    /// `None` before any stage has run.
+    pub fn stage_timings_label(&self) -> Option<String> {
+        let timings = self.stage_timings();
+        if timings.is_empty() {
+            return None;
+        }
+        let parts: Vec<String> = timings
+            .iter()
+            .map(|(stage, elapsed)| format!("{} {}ms", stage, elapsed.as_millis()))
+            .collect();
+        Some(parts.join(" · "))
+    }
+
+    /// Every stage's command line, exit status and full output from the
+    /// last build, for the diagnostics view.
+    fn diagnostics_text(&self) -> String {
+        if self.stage_outputs.is_empty() {
+            return String::from("[ no stages ran ]");
+        }
+        let mut text = String::new();
+        for stage in &self.stage_outputs {
+            text.push_str(&format!(
+                "==== {} {} ({})\n---- stdout\n{}\n---- stderr\n{}\n",
+                stage.tool,
+                stage.args.join(" "),
+                stage.status,
+                stage.stdout,
+                stage.stderr
+            ));
+        }
+        text
+    }
+
+    /// Records a failed stage's output, preceded by a dedicated explanation
+    /// for failure modes that are hard to read from the raw tool output.
+    fn push_stage_error(&mut self, tool: &str, stderr: &[u8]) {
+        let stderr = String::from_utf8_lossy(stderr);
+        let error = match explain_tool_error(&stderr, &self.unopt_ir) {
+            Some(explanation) => format!("{}\n\n{}", explanation, stderr),
+            None => stderr.into_owned(),
+        };
+        self.stage_errors.push((tool.to_string(), error));
+    }
+
+    /// Selects the `--top` entry, by IR or DSLX name. If the IR has no such
+    /// function the build stops with an error instead of optimizing another
+    /// entry.
+    fn select_pinned_top(&mut self) -> bool {
+        let Some(top) = &self.pinned_top else {
+            return true;
+        };
+        match self
+            .entry_points
+            .iter()
+            .position(|entry| entry == top || display_name(entry) == top)
+        {
+            Some(i) => {
+                self.selected_entry = i;
+                true
+            }
+            None => {
+                let error = format!(
+                    "no function {} in the IR; entries are: {}",
+                    top,
+                    self.entry_points.join(", ")
+                );
+                self.stage_errors.push((String::from("--top"), error));
+                false
+            }
+        }
+    }
+
+    fn run_pipeline(&mut self) {
+        let file_path = self.tool_file_path();
+        self.build_status = Some(BuildStatus::ConversionFailed);
+        let artifacts_ok = self.run_ir_stages(&file_path);
+        let tests = self.run_interpreter(&file_path, artifacts_ok);
+        if artifacts_ok {
+            self.build_status = Some(match tests {
+                Some(true) => BuildStatus::TestsPassed,
+                Some(false) => BuildStatus::TestsFailed,
+                None => BuildStatus::TestsSkipped,
+            });
+        }
+        self.update_error_message();
+    }
+
+    /// Whether the last build generated every artifact and no test failed;
+    /// a build without an interpreter to run the tests counts.
+    pub(crate) fn build_succeeded(&self) -> bool {
+        matches!(
+            self.build_status,
+            Some(BuildStatus::TestsPassed | BuildStatus::TestsSkipped)
+        )
+    }
+
+    /// Rebuilds the error pane text from the recorded stage errors.
+    fn update_error_message(&mut self) {
+        self.error_message = if self.stage_errors.is_empty() {
+            None
+        } else {
+            Some(
+                self.stage_errors
+                    .iter()
+                    .map(|(stage, error)| format!("{}: {}", stage, error))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            )
+        };
+        self.error_span = self.conversion_error_span();
+    }
+
+    /// The `file.x:LINE:COL` location in the IR conversion error, if it
+    /// refers to the watched file.
+    fn conversion_error_span(&self) -> Option<(usize, usize)> {
+        let (_, error) = self
+            .stage_errors
+            .iter()
+            .find(|(stage, _)| stage == "ir_converter_main")?;
+        let file_name = Path::new(self.file_path.as_deref()?).file_name()?;
+        let location_re = Regex::new(r"([^\s:]+\.x):(\d+):(\d+)").unwrap();
+        let caps = location_re
+            .captures_iter(error)
+            .find(|caps| Path::new(&caps[1]).file_name() == Some(file_name))?;
+        Some((caps[2].parse().ok()?, caps[3].parse().ok()?))
+    }
+
+    /// Moves the source cursor to the line of a conversion error so the code
+    /// pane scrolls it into view.
+    fn reveal_error_span(&mut self) {
+        if let Some((line, _)) = self.error_span {
+            let last_row = self.code.lines().count().saturating_sub(1);
+            self.source_cursor_row = line.saturating_sub(1).min(last_row);
+        }
+    }
+
+    /// The watched file's path as given to the tools, which run in the
+    /// configured working directory rather than ours.
+    fn tool_file_path(&self) -> String {
+        absolute_path(self.file_path.as_deref().expect("file_path not set"))
+    }
+
+    /// IR conversion, optimization and delay analysis; false once a stage
+    /// fails.
+    fn run_ir_stages(&mut self, file_path: &str) -> bool {
+        let mut ir_conv_args = vec![file_path.to_string()];
+        if let Some(ref stdlib) = self.dslx_stdlib_path {
+            ir_conv_args.extend(["--dslx_stdlib_path".to_string(), stdlib.clone()]);
+        }
+        let Some(ir_conv_output) = self.run_stage("ir_converter_main", &ir_conv_args) else {
+            return false;
+        };
+        if !ir_conv_output.status.success() {
+            self.push_stage_error("ir_converter_main", &ir_conv_output.stderr);
+            return false;
+        }
+        self.build_status = Some(BuildStatus::ConversionOk);
+        let unopt_ir = String::from_utf8_lossy(&ir_conv_output.stdout).to_string();
+        self.unopt_ir = unopt_ir.clone();
+        self.update_entry_points();
+
+        let opt_file = format!("{}.unopt.ir", file_path);
+        if !self.write_stage_artifact("ir_converter_main", &opt_file, &unopt_ir) {
+            return false;
+        }
+        if !self.select_pinned_top() {
+            return false;
+        }
+        let entry_name = &self.entry_points[self.selected_entry];
+        let top_arg = entry_name.to_string();
+        let Some(opt_output) =
+            self.run_stage("opt_main", &[opt_file, "--top".to_string(), top_arg])
+        else {
+            return false;
+        };
+        if !opt_output.status.success() {
+            self.push_stage_error("opt_main", &opt_output.stderr);
+            return false;
+        }
+        self.build_status = Some(BuildStatus::OptOk);
+        let opt_ir = String::from_utf8_lossy(&opt_output.stdout).to_string();
+        self.opt_stats = op_stats(&opt_ir, &self.entry_points[self.selected_entry]);
+        self.optimizer_diff = OptimizerDiff::of(&self.unopt_ir, &opt_ir);
+        // `prev_opt_ir` is only set with inline diffs on.
+        if !self.prev_opt_ir.is_empty() && self.prev_opt_ir != opt_ir {
+            self.opt_ir_diff = diff_lines(&self.prev_opt_ir, &opt_ir);
+        }
+        self.opt_ir = opt_ir.clone();
+
+        let opt_file = format!("{}.opt.ir", file_path);
+        if !self.write_stage_artifact("opt_main", &opt_file, &opt_ir) {
+            return false;
+        }
+        self.run_compare_opt(file_path);
+        self.run_codegen(&opt_file);
+        self.run_delay_stage(opt_file)
+    }
+
+    /// Proves the opt IR in `opt_file` equivalent to the unopt IR written for
+    /// `file_path` for the selected entry. Optional like codegen: the outcome
+    /// only shows in the equivalence tab. Being a SAT proof that can take
+    /// minutes, it is not part of the build but run on demand.
+    fn run_equivalence_check(&mut self, file_path: &str, opt_file: &str) {
+        if !self.tool_runner.has_tool(EQUIVALENCE_TOOL) {
+            self.equivalence = Equivalence::Unavailable;
+            return;
+        }
+        let Some(entry) = self.entry_points.get(self.selected_entry) else {
+            return;
+        };
+        let args = [
+            format!("{}.unopt.ir", file_path),
+            opt_file.to_string(),
+            format!("--top={}", entry),
+        ];
+        self.equivalence = match self.run_tool(EQUIVALENCE_TOOL, &args) {
+            Ok(output) => {
+                let mut report = String::from_utf8_lossy(&output.stdout).into_owned();
+                report.push_str(&String::from_utf8_lossy(&output.stderr));
+                Equivalence::Checked {
+                    equivalent: output.status.success(),
+                    report,
+                }
+            }
+            Err(e) => Equivalence::Checked {
+                equivalent: false,
+                report: format!("{} failed to start: {}", EQUIVALENCE_TOOL, e),
+            },
+        };
+    }
+
+    /// Generates Verilog for the opt IR in `opt_file`, pipelined with
+    /// `pipeline_stages` and `clock_period_ps` (a single stage when neither
+    /// is set) and scheduled with the current delay model, and reads back the
+    /// schedule codegen chose. Like the interpreter, codegen is optional: a
+    /// missing tool or a failure is shown in the Verilog tab without failing
+    /// the build.
+    fn run_codegen(&mut self, opt_file: &str) {
+        self.pipeline_schedule = None;
+        if !self.tool_runner.has_tool(CODEGEN_TOOL) {
+            self.verilog = format!("[ {} not found in XLSYNTH_TOOLS ]", CODEGEN_TOOL);
+            return;
+        }
+        let schedule_file = format!("{}.schedule.textproto", self.tool_file_path());
+        let mut args = vec![
+            opt_file.to_string(),
+            "--generator=pipeline".to_string(),
+            format!("--delay_model={}", self.delay_model),
+            format!("--output_schedule_path={}", schedule_file),
+        ];
+        // With only a clock period, codegen picks the fewest stages meeting it.
+        if self.pipeline_stages.is_some() || self.clock_period_ps.is_none() {
+            args.push(format!(
+                "--pipeline_stages={}",
+                self.pipeline_stages.unwrap_or(1)
+            ));
+        }
+        if let Some(period) = self.clock_period_ps {
+            args.push(format!("--clock_period_ps={}", period));
+        }
+        self.verilog = match self.run_tool(CODEGEN_TOOL, &args) {
+            Ok(output) if output.status.success() => {
+                self.pipeline_schedule = fs::read_to_string(&schedule_file)
+                    .ok()
+                    .and_then(|schedule| parse_schedule(&schedule, &self.opt_ir));
+                String::from_utf8_lossy(&output.stdout).to_string()
+            }
+            Ok(output) => format!(
+                "[ {} failed ]\n{}",
+                CODEGEN_TOOL,
+                String::from_utf8_lossy(&output.stderr)
+            ),
+            Err(e) => format!("[ {} failed to start ]\n{}", CODEGEN_TOOL, e),
+        };
+    }
+
+    /// Delay analysis of the opt IR in `opt_file` with the current delay
+    /// model; false if it fails.
+    fn run_delay_stage(&mut self, opt_file: String) -> bool {
+        let Some(delay_output) = self.run_stage(
+            "delay_info_main",
+            &[
+                opt_file,
+                "--delay_model".to_string(),
+                self.delay_model.clone(),
+            ],
+        ) else {
+            return false;
+        };
+        if !delay_output.status.success() {
+            self.push_stage_error("delay_info_main", &delay_output.stderr);
+            return false;
+        }
+        let delay_info = String::from_utf8_lossy(&delay_output.stdout).to_string();
+        self.delay_summary = parse_delay_summary(&delay_info);
+        self.prev_delay_info = std::mem::replace(&mut self.delay_info, delay_info);
+        self.delay_diff = diff_lines(&self.prev_delay_info, &self.delay_info);
+        true
+    }
+
+    /// Switches to the next of `delay_models`. When the last build wrote its
+    /// opt IR, only the delay and codegen stages are rerun against it;
+    /// otherwise the whole pipeline is. The previous model's delay info stays
+    /// available to the delay diff view, and an unknown model's error goes to
+    /// the error pane.
+    pub(crate) fn cycle_delay_model(&mut self) {
+        if self.delay_models.is_empty() {
+            return;
+        }
+        let next = self
+            .delay_models
+            .iter()
+            .position(|model| *model == self.delay_model)
+            .map_or(0, |i| (i + 1) % self.delay_models.len());
+        self.delay_model = self.delay_models[next].clone();
+        self.show_toast(format!("Delay model: {}", self.delay_model));
+        self.rerun(BuildJob::Delay);
+    }
+
+    /// Sets `pipeline_stages` `delta` stages away from the current schedule's
+    /// count, never below one, and reruns codegen against the last build's
+    /// opt IR.
+    pub(crate) fn adjust_pipeline_stages(&mut self, delta: isize) {
+        let current = self.pipeline_stages.unwrap_or_else(|| {
+            self.pipeline_schedule
+                .as_ref()
+                .map_or(1, |schedule| schedule.stages.len())
+        });
+        let stages = current.saturating_add_signed(delta).max(1);
+        if self.pipeline_stages == Some(stages) {
+            return;
+        }
+        self.pipeline_stages = Some(stages);
+        self.show_toast(format!("Pipeline stages: {}", stages));
+        self.rerun(BuildJob::Codegen);
+    }
+
+    /// Reruns `job` against the IR the last build wrote, like a build: on
+    /// the build worker when builds run in the background. Without that IR,
+    /// or while a different build is under way, the whole pipeline is rebuilt
+    /// instead.
+    fn rerun(&mut self, job: BuildJob) {
+        let inputs_missing = match job {
+            BuildJob::CompareOpt => self.unopt_ir.is_empty(),
+            _ => !self.stage_errors.is_empty() || self.opt_ir.is_empty(),
+        };
+        if self.file_empty
+            || self.staged_results.is_some()
+            || self.build.as_ref().is_some_and(|build| build.job != job)
+            || inputs_missing
+        {
+            self.check_and_run_conversion();
+            return;
+        }
+        let mut builder = self.builder();
+        builder.job = job;
+        builder.opt_ir = self.opt_ir.clone();
+        self.dispatch(builder, BuildPurpose::Show);
+    }
+
+    /// Runs this build copy's job.
+    fn run_job(&mut self) {
+        match self.job {
+            BuildJob::Full => {
+                self.update_entry_points();
+                self.run_conversion();
+            }
+            BuildJob::Delay => {
+                let opt_file = format!("{}.opt.ir", self.tool_file_path());
+                // Pipeline scheduling depends on the delay model too.
+                self.run_codegen(&opt_file);
+                self.run_delay_stage(opt_file);
+            }
+            BuildJob::Codegen => {
+                let opt_file = format!("{}.opt.ir", self.tool_file_path());
+                self.run_codegen(&opt_file);
+            }
+            BuildJob::CompareOpt => {
+                let file_path = self.tool_file_path();
+                self.run_compare_opt(&file_path);
+            }
+            BuildJob::Equivalence => {
+                let file_path = self.tool_file_path();
+                let opt_file = format!("{}.opt.ir", file_path);
+                self.run_equivalence_check(&file_path, &opt_file);
+            }
+        }
+    }
+
+    /// Takes over the outputs of a rerun, in place of those the last build
+    /// got from the same tools. A failed delay rerun fails the build.
+    fn apply_rerun(&mut self, mut built: App) {
+        if built.job == BuildJob::CompareOpt {
+            // The entry is dropped if it no longer exists.
+            self.compare_entry = built.compare_entry;
+            self.compare_opt_ir = built.compare_opt_ir;
+            return;
+        }
+        let tools = built.job.tools();
+        self.stage_outputs
+            .retain(|stage| !tools.contains(&stage.tool.as_str()));
+        self.stage_outputs.append(&mut built.stage_outputs);
+        self.warnings.retain(|warning| {
+            !tools
+                .iter()
+                .any(|tool| warning.starts_with(&format!("{}: ", tool)))
+        });
+        self.warnings.append(&mut built.warnings);
+        if built.job == BuildJob::Equivalence {
+            self.equivalence = built.equivalence;
+            return;
+        }
+        self.verilog = built.verilog;
+        self.pipeline_schedule = built.pipeline_schedule;
+        if built.job != BuildJob::Delay {
+            return;
+        }
+        if built.stage_errors.is_empty() {
+            self.delay_info = built.delay_info;
+            self.prev_delay_info = built.prev_delay_info;
+            self.delay_diff = built.delay_diff;
+            self.delay_summary = built.delay_summary;
+            return;
+        }
+        self.stage_errors.append(&mut built.stage_errors);
+        self.build_status = Some(BuildStatus::OptOk);
+        self.update_error_message();
+        if let Some(entry) = self.entry_points.get(self.selected_entry) {
+            self.entry_status.insert(entry.clone(), false);
+        }
+    }
+
+    /// Writes the unopt IR, opt IR, delay info and Verilog of the last build
+    /// to `dir`, each named after the source file, with a `manifest.json`
+    /// recording the entry, delay model and time. Artifacts the build did not
+    /// produce are left out. Returns the paths written.
+    pub(crate) fn export_artifacts(&self, dir: &Path) -> io::Result<Vec<PathBuf>> {
+        let source = self.file_path.as_deref().unwrap_or("");
+        let stem = Path::new(source)
+            .file_stem()
+            .map_or(Cow::Borrowed("artifacts"), |stem| stem.to_string_lossy());
+        // A Verilog tab without RTL holds a bracketed note instead.
+        let verilog = Some(self.verilog.as_str()).filter(|v| !v.is_empty() && !v.starts_with("[ "));
+        let artifacts = [
+            ("unopt.ir", Some(self.unopt_ir.as_str())),
+            ("opt.ir", Some(self.opt_ir.as_str())),
+            ("delay.txt", Some(self.delay_info.as_str())),
+            ("v", verilog),
+        ];
+        fs::create_dir_all(dir)?;
+        let mut written = Vec::new();
+        for (extension, contents) in artifacts {
+            let Some(contents) = contents.filter(|c| !c.is_empty()) else {
+                continue;
+            };
+            let path = dir.join(format!("{}.{}", stem, extension));
+            fs::write(&path, contents)?;
+            written.push(path);
+        }
+        let manifest = serde_json::json!({
+            "file": source,
+            "entry": self.entry_points.get(self.selected_entry),
+            "delay_model": self.delay_model,
+            "timestamp": chrono::Local::now().to_rfc3339(),
+            "artifacts": written
+                .iter()
+                .map(|path| path.file_name().unwrap_or_default().to_string_lossy())
+                .collect::<Vec<_>>(),
+        });
+        let manifest_path = dir.join("manifest.json");
+        fs::write(&manifest_path, format!("{:#}\n", manifest))?;
+        written.push(manifest_path);
+        Ok(written)
+    }
+
+    /// Exports the artifacts to `dir`, or `<file>-artifacts` by default,
+    /// reporting the outcome in the status area.
+    pub(crate) fn export_all(&mut self, dir: Option<&str>) {
+        let dir = match dir {
+            Some(dir) => PathBuf::from(dir),
+            None => PathBuf::from(format!(
+                "{}-artifacts",
+                self.file_path.as_deref().unwrap_or("")
+            )),
+        };
+        match self.export_artifacts(&dir) {
+            Ok(written) => self.show_toast(format!(
+                "Exported {} files to {}",
+                written.len(),
+                dir.display()
+            )),
+            Err(e) => self.show_toast(format!("Failed to export to {}: {}", dir.display(), e)),
+        }
+    }
+
+    /// Writes the selected entry's opt IR node graph as Graphviz DOT to
+    /// `<file>.opt.dot`.
+    fn export_dot(&mut self) {
+        let Some(entry) = self.entry_points.get(self.selected_entry).cloned() else {
+            self.show_toast(String::from("No entry selected"));
+            return;
+        };
+        let Some(dot) = ir_dot(&self.opt_ir, &entry) else {
+            self.show_toast(format!("No function {} in the opt IR", entry));
+            return;
+        };
+        let path = format!("{}.opt.dot", self.file_path.as_deref().unwrap_or(""));
+        match self.write_artifact(&path, &dot) {
+            Ok(()) => self.show_toast(format!("Exported {} graph to {}", entry, path)),
+            Err(e) => self.show_toast(format!("Failed to write {}: {}", path, e)),
+        }
+    }
+
+    /// Optimizes the unopt IR written for `file_path` with the comparison
+    /// entry as top. Failures are shown in place of its IR rather than
+    /// failing the build; an entry that no longer exists ends the comparison.
+    fn run_compare_opt(&mut self, file_path: &str) {
+        let Some(entry) = self.compare_entry.clone() else {
+            return;
+        };
+        if !self.entry_points.contains(&entry) {
+            self.compare_entry = None;
+            self.compare_opt_ir.clear();
+            return;
+        }
+        let unopt_file = format!("{}.unopt.ir", file_path);
+        self.compare_opt_ir =
+            match self.run_tool("opt_main", &[unopt_file, "--top".to_string(), entry]) {
+                Ok(output) if output.status.success() => {
+                    String::from_utf8_lossy(&output.stdout).to_string()
+                }
+                Ok(output) => format!(
+                    "[ opt_main failed ]\n{}",
+                    String::from_utf8_lossy(&output.stderr)
+                ),
+                Err(e) => format!("[ opt_main failed to start ]\n{}", e),
+            };
+    }
+
+    /// Pins the selected entry as the one to compare against, or ends the
+    /// comparison if one is pinned.
+    pub(crate) fn toggle_compare_entry(&mut self) {
+        if self.compare_entry.take().is_some() {
+            self.compare_opt_ir.clear();
+            if self
+                .build
+                .as_ref()
+                .is_some_and(|build| build.job == BuildJob::CompareOpt)
+            {
+                self.cancel_build();
+            }
+            return;
+        }
+        let Some(entry) = self.entry_points.get(self.selected_entry).cloned() else {
+            return;
+        };
+        self.show_toast(format!(
+            "Comparing opt IR against {}; select another entry",
+            entry
+        ));
+        self.compare_entry = Some(entry);
+        if self.file_path.is_some() {
+            self.rerun(BuildJob::CompareOpt);
+        }
+    }
+
+    /// Runs the DSLX tests when the interpreter is available; returns whether
+    /// they passed, or `None` when there is no interpreter. A JIT comparison
+    /// mismatch only counts as passing with `compare_mismatch_non_fatal` and
+    /// when the IR stages succeeded.
+    fn run_interpreter(&mut self, file_path: &str, artifacts_ok: bool) -> Option<bool> {
+        if !self.tool_runner.has_tool("dslx_interpreter_main") {
+            return None;
+        }
+        let mut interpreter_args = vec![file_path.to_string()];
+        if let Some(ref stdlib) = self.dslx_stdlib_path {
+            interpreter_args.extend(["--dslx_stdlib_path".to_string(), stdlib.clone()]);
+        }
+        if self.jit_compare {
+            interpreter_args.push("--compare=jit".to_string());
+        }
+        if let Some(test) = &self.test_filter {
+            interpreter_args.push(format!("--test_filter={}", regex::escape(test)));
+        }
+        let Some(interpreter_output) = self.run_stage("dslx_interpreter_main", &interpreter_args)
+        else {
+            return Some(false);
+        };
+        self.interpreter_ok = Some(interpreter_output.status.success());
+        if interpreter_output.status.success() {
+            let output = if interpreter_output.stdout.is_empty() {
+                interpreter_output.stderr
+            } else {
+                interpreter_output.stdout
+            };
+            self.test_output = Some(String::from_utf8_lossy(&output).to_string());
+            Some(true)
+        } else if artifacts_ok
+            && self.compare_mismatch_non_fatal
+            && is_compare_mismatch(&String::from_utf8_lossy(&interpreter_output.stderr))
+        {
+            // The essential stages all succeeded; keep their artifacts and
+            // surface the discrepancy alongside the test output.
+            let stderr = String::from_utf8_lossy(&interpreter_output.stderr).to_string();
+            self.warnings
+                .push("interpreter/JIT comparison mismatch (see test output)".into());
+            self.test_output = Some(stderr);
+            Some(true)
+        } else {
+            self.push_stage_error("dslx_interpreter_main", &interpreter_output.stderr);
+            Some(false)
+        }
+    }
+
+    fn nav_state(&self) -> NavState {
+        NavState {
+            selected_entry: self.selected_entry,
+            selected_tab: self.selected_tab,
+            results_scroll: self.results_scroll,
+        }
+    }
+
+    /// Records the current navigation state before a user-initiated entry
+    /// change. A fresh action invalidates anything that could be redone.
+    pub(crate) fn push_nav_undo(&mut self) {
+        // Choosing an entry by hand ends a `--top` pin.
+        self.pinned_top = None;
+        if self.nav_undo_stack.len() == NAV_UNDO_LIMIT {
+            self.nav_undo_stack.remove(0);
+        }
+        self.nav_undo_stack.push(self.nav_state());
+        self.nav_redo_stack.clear();
+    }
+
+    fn restore_nav(&mut self, state: NavState) {
+        let entry_changed = state.selected_entry != self.selected_entry;
+        self.selected_entry = state
+            .selected_entry
+            .min(self.entry_points.len().saturating_sub(1));
+        self.selected_tab = state.selected_tab;
+        self.results_scroll = state.results_scroll;
+        if entry_changed {
+            self.check_and_run_conversion();
+        }
+    }
+
+    pub(crate) fn undo_nav(&mut self) {
+        if let Some(state) = self.nav_undo_stack.pop() {
+            self.nav_redo_stack.push(self.nav_state());
+            self.restore_nav(state);
+        }
+    }
+
+    pub(crate) fn redo_nav(&mut self) {
+        if let Some(state) = self.nav_redo_stack.pop() {
+            self.nav_undo_stack.push(self.nav_state());
+            self.restore_nav(state);
+        }
+    }
+
+    /// Copies the selected entry's name to the clipboard: the mangled IR
+    /// name passed as `--top`, or with `dslx_name` the DSLX function name.
+    pub(crate) fn copy_entry_name(&mut self, dslx_name: bool) {
+        let Some(entry) = self.entry_points.get(self.selected_entry).cloned() else {
+            return;
+        };
+        let (form, name) = if dslx_name {
+            ("DSLX name", display_name(&entry))
+        } else {
+            ("top name", entry.as_str())
+        };
+        match copy_to_clipboard(name) {
+            Ok(()) => self.show_toast(format!("Copied {} {}", form, name)),
+            Err(e) => self.show_toast(format!("Failed to copy {}: {}", form, e)),
+        }
+    }
+
+    /// Copies the full text of the results pane's tab (unfolded, for the IR
+    /// tabs) to the clipboard. Without a clipboard it is written to a file in
+    /// the temp directory instead, whose path is shown.
+    pub(crate) fn copy_pane(&mut self) {
+        let (name, text) = match self.selected_tab {
+            _ if self.show_diagnostics => ("diagnostics", self.diagnostics_text()),
+            0 => ("unopt", self.unopt_ir.clone()),
+            1 => ("opt", self.opt_ir.clone()),
+            tab => (TABS[tab].0, self.tab_text().into_owned()),
+        };
+        let lines = text.lines().count();
+        if clipboard_available() && copy_to_clipboard(&text).is_ok() {
+            self.show_toast(format!("Copied {} lines", lines));
+            return;
+        }
+        let path = std::env::temp_dir().join(format!("dslx-watch-tui-{}.txt", name));
+        match fs::write(&path, &text) {
+            Ok(()) => self.show_toast(format!(
+                "No clipboard; wrote {} lines to {}",
+                lines,
+                path.display()
+            )),
+            Err(e) => self.show_toast(format!("Failed to copy: {}", e)),
+        }
+    }
+
+    /// Selects the tab after the selected one in the configured order.
+    pub(crate) fn next_tab(&mut self) {
+        let position = self
+            .tab_order
+            .iter()
+            .position(|(tab, _)| *tab == self.selected_tab)
+            .map_or(0, |p| (p + 1) % self.tab_order.len());
+        self.select_tab(self.tab_order[position].0);
+    }
+
+    /// Selects `tab` if it is among the configured tabs.
+    pub(crate) fn select_tab(&mut self, tab: usize) {
+        if self.tab_order.iter().any(|(shown, _)| *shown == tab) {
+            self.tab_scrolls[self.selected_tab] = self.results_scroll;
+            self.tab_hscrolls[self.selected_tab] = self.results_hscroll;
+            self.selected_tab = tab;
+            self.results_scroll = self.tab_scrolls[tab];
+            self.results_hscroll = self.tab_hscrolls[tab];
+        }
+    }
+
+    /// The view state to remember for the next session on the active file.
+    pub(crate) fn saved_state(&self) -> SavedState {
+        let mut tab_scrolls = self.tab_scrolls;
+        if !self.show_diagnostics {
+            tab_scrolls[self.selected_tab] = self.results_scroll;
+        }
+        SavedState {
+            selected_tab: TABS[self.selected_tab].0.to_string(),
+            selected_entry: self.selected_entry,
+            delay_model: self.delay_model.clone(),
+            tab_scrolls: TABS
+                .iter()
+                .zip(tab_scrolls)
+                .filter(|(_, scroll)| *scroll > 0)
+                .map(|((stage, _), scroll)| (stage.to_string(), scroll))
+                .collect(),
+            scrolled_content: self.scrolled_content.to_vec(),
+        }
+    }
+
+    /// Restores the view state of an earlier session. An entry index beyond
+    /// the current file's entries falls back to the first entry once they are
+    /// known, and a tab or delay model no longer configured is ignored.
+    pub(crate) fn restore_state(&mut self, state: SavedState) {
+        for (i, (stage, _)) in TABS.iter().enumerate() {
+            if let Some(&scroll) = state.tab_scrolls.get(*stage) {
+                self.tab_scrolls[i] = scroll;
+            }
+        }
+        for (hash, saved) in self.scrolled_content.iter_mut().zip(state.scrolled_content) {
+            *hash = saved;
+        }
+        if let Some(tab) = TABS
+            .iter()
+            .position(|(stage, _)| *stage == state.selected_tab)
+            .filter(|tab| self.tab_order.iter().any(|(shown, _)| shown == tab))
+        {
+            self.selected_tab = tab;
+        }
+        self.results_scroll = self.tab_scrolls[self.selected_tab];
+        self.selected_entry = state.selected_entry;
+        if self.delay_models.contains(&state.delay_model) {
+            self.delay_model = state.delay_model;
+        }
+    }
+
+    /// Scrolls each IR and delay tab whose content a build replaced back to
+    /// the top left. Checked per frame, so results held back in manual
+    /// apply mode keep their position until shown.
+    pub(crate) fn reset_replaced_scrolls(&mut self) {
+        let hashes = [&self.unopt_ir, &self.opt_ir, &self.delay_info].map(|text| fnv1a_hash(text));
+        for (tab, hash) in hashes.into_iter().enumerate() {
+            if self.scrolled_content[tab] == hash {
+                continue;
+            }
+            self.scrolled_content[tab] = hash;
+            self.tab_scrolls[tab] = 0;
+            self.tab_hscrolls[tab] = 0;
+            if self.selected_tab == tab && !self.show_diagnostics {
+                self.results_scroll = 0;
+                self.results_hscroll = 0;
+                self.results_cursor = 0;
+            }
+        }
+    }
+
+    /// Position of the active file among the watched files and how many
+    /// there are, when there is more than one.
+    pub(crate) fn file_position(&self) -> Option<(usize, usize)> {
+        (self.files.len() > 1).then_some((self.active_file + 1, self.files.len()))
+    }
+
+    /// Notes that the inactive file at `index` changed on disk, so it is
+    /// rebuilt when switched to.
+    pub(crate) fn mark_file_changed(&mut self, index: usize) {
+        if let Some(Some(state)) = self.files.get_mut(index) {
+            state.changed = true;
+            state.last_change_at = Some(Instant::now());
+        }
+    }
+
+    /// Makes the watched file `offset` places after the active one active,
+    /// wrapping around, and rebuilds it if it changed meanwhile. A build of
+    /// the file being left is abandoned and redone when it is next active.
+    pub(crate) fn switch_file(&mut self, offset: usize) {
+        if self.files.len() < 2 {
+            return;
+        }
+        let next = (self.active_file + offset) % self.files.len();
+        let was_building = self.is_building();
+        self.cancel_build();
+        let mut state = self.files[next]
+            .take()
+            .expect("inactive files keep their state");
+        let changed = state.changed;
+        self.swap_file_state(&mut state);
+        state.changed = was_building;
+        self.files[self.active_file] = Some(state);
+        self.active_file = next;
+
+        self.nav_undo_stack.clear();
+        self.nav_redo_stack.clear();
+        self.bookmarks.clear();
+        self.inspected_node = None;
+        self.opt_ir_changed_at = None;
+        self.results_cursor = 0;
+        let file_path = self.file_path.clone().unwrap_or_default();
+        self.show_toast(format!("Switched to {}", file_path));
+        if changed {
+            if self.paused {
+                self.note_paused_change();
+            } else {
+                self.reload_and_rebuild();
+            }
+        }
+    }
+
+    /// Counts a file change while rebuilds are paused, rereading the file so
+    /// the source pane stays current without starting a build.
+    pub(crate) fn note_paused_change(&mut self) {
+        self.pending_changes += 1;
+        let file_path = self.file_path.clone().expect("file_path not set");
+        if let Ok(code) = read_dslx_source(&file_path) {
+            self.code = code;
+            self.clamp_source_cursor();
+            self.last_update = Some(chrono::Local::now());
+        }
+    }
+
+    fn swap_file_state(&mut self, state: &mut FileState) {
+        std::mem::swap(&mut self.file_path, &mut state.file_path);
+        std::mem::swap(&mut self.code, &mut state.code);
+        self.swap_results(&mut state.results);
+        std::mem::swap(&mut self.staged_results, &mut state.staged_results);
+        std::mem::swap(&mut self.entry_points, &mut state.entry_points);
+        std::mem::swap(&mut self.entry_signatures, &mut state.entry_signatures);
+        std::mem::swap(&mut self.selected_entry, &mut state.selected_entry);
+        std::mem::swap(&mut self.entry_status, &mut state.entry_status);
+        std::mem::swap(&mut self.compare_entry, &mut state.compare_entry);
+        std::mem::swap(&mut self.test_filter, &mut state.test_filter);
+        std::mem::swap(&mut self.input_warning, &mut state.input_warning);
+        std::mem::swap(&mut self.last_update, &mut state.last_update);
+        std::mem::swap(&mut self.last_code_hash, &mut state.last_code_hash);
+        std::mem::swap(&mut self.file_empty, &mut state.file_empty);
+        std::mem::swap(&mut self.source_cursor_row, &mut state.source_cursor_row);
+        std::mem::swap(&mut self.source_scroll, &mut state.source_scroll);
+        std::mem::swap(&mut self.last_change_at, &mut state.last_change_at);
+        std::mem::swap(&mut self.refreshed_at, &mut state.refreshed_at);
+        std::mem::swap(&mut self.last_build_at, &mut state.last_build_at);
+        std::mem::swap(
+            &mut self.last_build_duration,
+            &mut state.last_build_duration,
+        );
+    }
+
+    /// The plain text shown in the selected results tab.
+    pub(crate) fn tab_text(&self) -> Cow<'_, str> {
+        if self.show_diagnostics {
+            return Cow::Owned(self.diagnostics_text());
+        }
+        match self.selected_tab {
+            0 => self.folded_ir(&self.unopt_ir),
+            1 => self.folded_ir(&self.opt_ir),
+            2 => Cow::Borrowed(&self.delay_info),
+            3 => Cow::Owned(call_graph_summary(&self.unopt_ir)),
+            4 => Cow::Borrowed(&self.hook_log),
+            5 => Cow::Owned(stats_summary(&self.opt_ir, &self.unopt_ir)),
+            6 => Cow::Owned(verification_summary(&self.opt_ir, self.test_log())),
+            7 => match &self.pipeline_schedule {
+                Some(schedule) => Cow::Owned(format!("{}\n\n{}", schedule.table(), self.verilog)),
+                None => Cow::Borrowed(&self.verilog),
+            },
+            8 => Cow::Owned(self.optimizer_diff.text()),
+            9 if self.is_checking_equivalence() => Cow::Borrowed("[ checking equivalence… ]"),
+            9 => Cow::Owned(self.equivalence.summary()),
+            _ => Cow::Borrowed(""),
+        }
+    }
+
+    /// Tests passed and run in the last build, for the status line badge;
+    /// `None` when the interpreter did not run or reported no tests.
+    pub(crate) fn test_badge_counts(&self) -> Option<(usize, usize)> {
+        let (log, _) = self.test_log()?;
+        let (ran, failed) = test_counts(log);
+        (ran > 0).then(|| (ran.saturating_sub(failed), ran))
+    }
+
+    /// The interpreter's output from the last build and whether the tests
+    /// passed; `None` when the interpreter did not run.
+    fn test_log(&self) -> Option<(&str, bool)> {
+        let passed = self.interpreter_ok?;
+        let log = match &self.test_output {
+            Some(output) => output.as_str(),
+            _ => self
+                .stage_errors
+                .iter()
+                .find(|(stage, _)| stage == "dslx_interpreter_main")
+                .map_or("", |(_, error)| error.as_str()),
+        };
+        Some((log, passed))
+    }
+
+    fn folded_ir<'a>(&self, ir: &'a str) -> Cow<'a, str> {
+        if self.folded_functions.is_empty() {
+            Cow::Borrowed(ir)
+        } else {
+            Cow::Owned(fold_functions(ir, &self.folded_functions))
+        }
+    }
+
+    /// Names of the `#[test]` functions in the source, in order.
+    pub(crate) fn test_names(&self) -> Vec<String> {
+        let test_re = Regex::new(r"#\[test\]\s*fn (\w+)").unwrap();
+        test_re
+            .captures_iter(&self.code)
+            .map(|cap| cap[1].to_string())
+            .collect()
+    }
+
+    /// Moves the test filter to the next test in the source, wrapping back
+    /// to running every test, and rebuilds.
+    pub(crate) fn cycle_test_filter(&mut self) {
+        let names = self.test_names();
+        let next = match &self.test_filter {
+            None => names.first(),
+            Some(current) => names
+                .iter()
+                .position(|n| n == current)
+                .and_then(|i| names.get(i + 1)),
+        };
+        self.test_filter = next.cloned();
+        self.show_toast(match &self.test_filter {
+            Some(test) => format!("Running only test {}", test),
+            None => String::from("Running all tests"),
+        });
+        self.check_and_run_conversion();
+    }
+
+    /// Folds or unfolds the IR function under the results cursor.
+    pub(crate) fn toggle_fold(&mut self) {
+        if self.show_diagnostics || self.selected_tab > 1 {
+            return;
+        }
+        if let Some(function) = function_at_line(&self.tab_text(), self.results_cursor) {
+            if !self.folded_functions.remove(&function) {
+                self.folded_functions.insert(function);
+            }
+        }
+    }
+
+    /// Brings the search matches up to date with the selected tab's text.
+    pub(crate) fn update_search(&mut self) {
+        if let Some(mut search) = self.search.take() {
+            search.update(&self.tab_text());
+            self.search = Some(search);
+        }
+    }
+
+    /// Scrolls the results pane to the current search match.
+    pub(crate) fn scroll_to_search_match(&mut self) {
+        self.update_search();
+        if let Some((line, _, _)) = self.search.as_ref().and_then(Search::current_match) {
+            // Clamped against the content length at draw time.
+            self.results_scroll = scroll_into_view(
+                self.results_scroll,
+                line,
+                self.results_viewport,
+                self.scroll_off,
+            );
+            self.results_cursor = line;
+        }
+    }
+
+    /// Saves the results scroll position of the selected tab as `digit`.
+    pub(crate) fn set_bookmark(&mut self, digit: char) {
+        let hash = fnv1a_hash(&self.tab_text());
+        self.bookmarks
+            .insert((self.selected_tab, digit), (hash, self.results_scroll));
+        self.show_toast(format!("Bookmark {} set", digit));
+    }
+
+    /// Scrolls back to bookmark `digit`, dropping it if the tab's text has
+    /// changed since it was set.
+    pub(crate) fn jump_to_bookmark(&mut self, digit: char) {
+        let key = (self.selected_tab, digit);
+        match self.bookmarks.get(&key).copied() {
+            Some((hash, scroll)) if hash == fnv1a_hash(&self.tab_text()) => {
+                self.results_scroll = scroll;
+            }
+            Some(_) => {
+                self.bookmarks.remove(&key);
+                self.show_toast(format!("Bookmark {} cleared: content changed", digit));
+            }
+            None => self.show_toast(format!("No bookmark {} in this tab", digit)),
+        }
+    }
+
+    /// When the file was last updated, as "updated at <time>" in the
+    /// configured format or, with relative times, "updated 3s ago".
+    pub(crate) fn updated_label(&self) -> Option<String> {
+        let time = self.last_update?;
+        if !self.relative_time {
+            return Some(format!("updated at {}", time.format(&self.time_format)));
+        }
+        let secs = (chrono::Local::now() - time).num_seconds().max(0) as u64;
+        Some(format!(
+            "updated {} ago",
+            format_age(Duration::from_secs(secs))
+        ))
+    }
+
+    /// Compact status-line summary of how long ago the file last changed (or
+    /// was refreshed with `r`) and was last built, or a spinner while a build
+    /// runs.
+    pub(crate) fn timing_label(&self) -> Option<String> {
+        // A refresh with `r` since the last file change is reported instead.
+        let refreshed_at = self.refreshed_at.filter(|&at| {
+            self.last_change_at
+                .is_none_or(|changed_at| at >= changed_at)
+        });
+        let trigger = match refreshed_at {
+            Some(at) => Some(format!("refreshed {} ago", format_age(at.elapsed()))),
+            None => self
+                .last_change_at
+                .map(|at| format!("changed {} ago", format_age(at.elapsed()))),
+        };
+        if let Some(build) = &self.build {
+            let frame = build.started_at.elapsed().as_millis() / 100;
+            let spinner = SPINNER[frame as usize % SPINNER.len()];
+            if refreshed_at.is_some_and(|at| build.started_at >= at) {
+                return Some(format!("{} refreshing…", spinner));
+            }
+            return Some(match self.last_change_at {
+                Some(at) if build.purpose != BuildPurpose::Show => format!(
+                    "{} change detected {} ago, building…",
+                    spinner,
+                    format_age(at.elapsed())
+                ),
+                _ => format!("{} building…", spinner),
+            });
+        }
+        let built = self.last_build_at.map(|at| {
+            format!(
+                "built {} ago in {:.1}s",
+                format_age(at.elapsed()),
+                self.last_build_duration.as_secs_f64()
+            )
+        });
+        match (trigger, built) {
+            (Some(trigger), Some(built)) => Some(format!("{} · {}", trigger, built)),
+            (None, built) => built,
+            (trigger, None) => trigger,
+        }
+    }
+
+    /// Rereads the file and rebuilds it whether or not anything changed, for
+    /// when the environment did: rebuilt tools, an edited stdlib.
+    pub(crate) fn refresh(&mut self) {
+        let file_path = self.file_path.clone().expect("file_path not set");
+        if let Ok(code) = read_dslx_source(&file_path) {
+            self.code = code;
+            self.clamp_source_cursor();
+        }
+        self.refreshed_at = Some(Instant::now());
+        self.check_and_run_conversion();
+    }
+
+    pub(crate) fn show_toast(&mut self, message: String) {
+        self.toast = Some((message, std::time::Instant::now()));
+    }
+
+    /// Runs a command entered on the `:` command line.
+    pub(crate) fn execute_command(&mut self, command: &str) {
+        match command.trim() {
+            "" => {}
+            "report" => {
+                let path = format!("{}-report.html", self.file_path.as_deref().unwrap_or(""));
+                let html = report::generate_html_report(self);
+                match self.write_artifact(&path, &html) {
+                    Ok(()) => self.show_toast(format!("Report written to {}", path)),
+                    Err(e) => self.show_toast(format!("Failed to write {}: {}", path, e)),
+                }
+            }
+            "export csv" | "export json" => {
+                let entries = parse_delay_info(&self.delay_info);
+                let (extension, contents) = if command.trim().ends_with("csv") {
+                    ("csv", delay_csv(&entries))
+                } else {
+                    ("json", delay_json(&entries))
+                };
+                let path = format!(
+                    "{}.delay.{}",
+                    self.file_path.as_deref().unwrap_or(""),
+                    extension
+                );
+                match self.write_artifact(&path, &contents) {
+                    Ok(()) => self.show_toast(format!(
+                        "Exported {} delay rows to {}",
+                        entries.len(),
+                        path
+                    )),
+                    Err(e) => self.show_toast(format!("Failed to write {}: {}", path, e)),
+                }
+            }
+            "export dot" => self.export_dot(),
+            "export all" => self.export_all(None),
+            "reduce" => self.reduce_failure(),
+            "equiv" => {
+                self.equivalence = Equivalence::NotRun;
+                self.equivalence_requested = true;
+                self.poll_equivalence();
+            }
+            "node" => self.inspected_node = None,
+            other => {
+                if let Some(name) = other.strip_prefix("node ") {
+                    self.inspected_node = Some(name.trim().to_string());
+                } else if let Some(dir) = other.strip_prefix("export all ") {
+                    self.export_all(Some(dir.trim()));
+                } else {
+                    self.show_toast(format!("Unknown command: {}", other));
+                }
+            }
+        }
+    }
+
+    /// Whether the last build's tests passed only because there were none.
+    pub(crate) fn no_tests_ran(&self) -> bool {
+        self.build_status == Some(BuildStatus::TestsPassed)
+            && self.interpreter_ok == Some(true)
+            && self.test_output.as_deref().map(tests_run) == Some(0)
+    }
+
+    /// Directory of the tool binaries, when the runner loads them from one.
+    pub(crate) fn tools_dir(&self) -> Option<PathBuf> {
+        self.tool_runner.tools_dir().map(Path::to_path_buf)
+    }
+
+    /// The modules the active file imports, looked up as the tools do: in
+    /// `workdir`, then the file's directory, then the DSLX stdlib.
+    pub(crate) fn imported_files(&self, workdir: &Path) -> Vec<PathBuf> {
+        let file_dir = self
+            .file_path
+            .as_deref()
+            .and_then(|path| Path::new(path).parent())
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        let mut search_dirs = vec![workdir.to_path_buf(), file_dir.to_path_buf()];
+        search_dirs.extend(self.dslx_stdlib_path.as_deref().map(PathBuf::from));
+        imported_files(&self.code, &search_dirs)
+    }
+
+    /// Notes that an imported module changed, so the next reload rebuilds
+    /// even though the file itself did not change.
+    pub(crate) fn import_changed(&mut self, path: &Path) {
+        self.last_code_hash = None;
+        let name = path.file_name().unwrap_or(path.as_os_str());
+        self.show_toast(format!("Imported {} changed", name.to_string_lossy()));
+    }
+
+    /// Rebuilds after a tool binary changed, once every required binary is
+    /// present and executable again.
+    pub(crate) fn rebuild_with_updated_tools(&mut self) {
+        let missing = missing_tools(&self.tool_runner);
+        if missing.is_empty() {
+            self.check_and_run_conversion();
+        } else {
+            self.error_message = Some(format!(
+                "Required binaries missing or not executable: {}",
+                missing.join(", ")
+            ));
+            self.stage_errors.clear();
+            self.build_status = Some(BuildStatus::NotRun);
+        }
+    }
+
+    /// Toasts a one-line summary of how the opt IR changed since `previous`.
+    fn show_ir_changelog(&mut self, previous: &str) {
+        if previous.is_empty() || self.opt_ir.is_empty() {
+            return;
+        }
+        let entry = self
+            .entry_points
+            .get(self.selected_entry)
+            .map_or("", String::as_str);
+        let changelog = ir_changelog(previous, &self.opt_ir, entry);
+        if !changelog.is_empty() {
+            self.show_toast(format!("Since last build: {}", changelog));
+        }
+    }
+
+    /// Starts shrinking the source, on a background thread, to a smaller
+    /// file on which the first failing stage still fails the same way.
+    /// Progress is shown in the toast, and the result is written to
+    /// `<file>.reduced.x` by `poll_reduce`. The watched file is never
+    /// modified.
+    fn reduce_failure(&mut self) {
+        if let Some(reduction) = &self.reduction {
+            let message = format!(
+                "Already reducing the {} failure (Esc cancels)",
+                reduction.stage
+            );
+            self.show_toast(message);
+            return;
+        }
+        let Some((stage, _)) = self.stage_errors.first().cloned() else {
+            self.show_toast(String::from("Nothing to reduce: the build succeeded"));
+            return;
+        };
+        // Candidates sit beside the original so relative imports resolve.
+        let scratch = PathBuf::from(format!("{}.reduce-tmp.x", self.tool_file_path()));
+        let top = self
+            .entry_points
+            .get(self.selected_entry)
+            .cloned()
+            .unwrap_or_default();
+        let stdlib = self.dslx_stdlib_path.clone();
+        let delay_model = self.delay_model.clone();
+        let runner = self.tool_runner.clone();
+        let code = self.code.clone();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let (tx, rx) = mpsc::channel();
+        let thread_stage = stage.clone();
+        let thread_cancel = Arc::clone(&cancel);
+        std::thread::spawn(move || {
+            let failure_of = |source: &str| {
+                fs::write(&scratch, source).ok()?;
+                run_until(
+                    &runner,
+                    &scratch,
+                    stdlib.as_deref(),
+                    &top,
+                    &delay_model,
+                    &thread_stage,
+                )
+            };
+            let mut candidates = 0;
+            let mut lines = code.lines().count();
+            let reduced = failure_of(&code).map(|target| {
+                reduce_lines(&code, REDUCE_TIME_LIMIT, &thread_cancel, |source| {
+                    let reproduces = failure_of(source).as_ref() == Some(&target);
+                    candidates += 1;
+                    if reproduces {
+                        lines = source.lines().count();
+                    }
+                    // The receiver is gone once the reduction is cancelled.
+                    let _ = tx.send(ReduceProgress::Tried { candidates, lines });
+                    reproduces
+                })
+            });
+            for suffix in ["", ".unopt.ir", ".opt.ir"] {
+                let _ = fs::remove_file(format!("{}{}", scratch.display(), suffix));
+            }
+            let _ = tx.send(ReduceProgress::Done(reduced));
+        });
+        self.show_toast(format!("Reducing the {} failure… (Esc cancels)", stage));
+        self.reduction = Some(Reduction {
+            stage,
+            cancel,
+            progress: rx,
+        });
+    }
+
+    /// Starts the equivalence check of the last build once the equivalence
+    /// tab is shown or `:equiv` asked for it, and no build is under way. A
+    /// build without opt IR to check leaves it not run.
+    pub(crate) fn poll_equivalence(&mut self) {
+        let shown = self.selected_tab == 9 && !self.show_diagnostics;
+        if !(shown || self.equivalence_requested)
+            || !matches!(self.equivalence, Equivalence::NotRun)
+            || self.build.is_some()
+        {
+            return;
+        }
+        let requested = std::mem::take(&mut self.equivalence_requested);
+        if self.file_empty
+            || self.staged_results.is_some()
+            || !self.stage_errors.is_empty()
+            || self.opt_ir.is_empty()
+        {
+            if requested {
+                self.show_toast(String::from("No opt IR to check"));
+            }
+            return;
+        }
+        if requested {
+            self.show_toast(String::from("Checking equivalence…"));
+        }
+        self.rerun(BuildJob::Equivalence);
+    }
+
+    /// Whether the equivalence check is running.
+    fn is_checking_equivalence(&self) -> bool {
+        self.build
+            .as_ref()
+            .is_some_and(|build| build.job == BuildJob::Equivalence)
+    }
+
+    /// Shows the progress of a running `:reduce` and, once it has finished,
+    /// writes its result.
+    pub(crate) fn poll_reduce(&mut self) {
+        let Some(reduction) = &self.reduction else {
+            return;
+        };
+        let mut done = None;
+        let mut tried = None;
+        for progress in reduction.progress.try_iter() {
+            match progress {
+                ReduceProgress::Tried { candidates, lines } => tried = Some((candidates, lines)),
+                ReduceProgress::Done(reduced) => done = Some(reduced),
+            }
+        }
+        let stage = reduction.stage.clone();
+        let Some(reduced) = done else {
+            if let Some((candidates, lines)) = tried {
+                self.show_toast(format!(
+                    "Reducing the {} failure: {} candidates tried, now {} lines (Esc cancels)",
+                    stage, candidates, lines
+                ));
+            }
+            return;
+        };
+        self.reduction = None;
+        let Some(reduced) = reduced else {
+            self.show_toast(format!("Could not reproduce the {} failure", stage));
+            return;
+        };
+        let path = format!("{}.reduced.x", self.file_path.as_deref().unwrap_or(""));
+        let message = match self.write_artifact(&path, &reduced) {
+            Ok(()) => format!(
+                "Reduced {} to {} lines in {}",
+                stage,
+                reduced.lines().count(),
+                path
+            ),
+            Err(e) => format!("Failed to write {}: {}", path, e),
+        };
+        self.show_toast(message);
+    }
+
+    /// Whether a `:reduce` is running.
+    pub(crate) fn is_reducing(&self) -> bool {
+        self.reduction.is_some()
+    }
+
+    /// Stops a running `:reduce` without writing its result.
+    pub(crate) fn cancel_reduce(&mut self) {
+        if let Some(reduction) = self.reduction.take() {
+            reduction.cancel.store(true, Ordering::Relaxed);
+            self.show_toast(format!(
+                "Cancelled reducing the {} failure",
+                reduction.stage
+            ));
+        }
+    }
+
+    /// Re-reads the watched file and rebuilds all artifacts from it.
+    pub(crate) fn reload_and_rebuild(&mut self) {
+        let file_path = self.file_path.clone().expect("file_path not set");
+        let source = read_dslx_source(&file_path);
+        // Restores and no-op saves still fire change events.
+        if let Ok(code) = &source {
+            if self.last_code_hash == Some(fnv1a_hash(code)) {
+                self.show_toast(String::from("No changes detected, skipping rebuild"));
+                return;
+            }
+        }
+        self.last_update = Some(chrono::Local::now());
+        match source {
+            Ok(code) => {
+                self.code = code;
+                self.clamp_source_cursor();
+                self.rebuild(if self.manual_apply {
+                    BuildPurpose::Stage
+                } else {
+                    BuildPurpose::Announce
+                });
+            }
+            Err(msg) => {
+                self.cancel_build();
+                self.error_message = Some(msg);
+                self.stage_errors.clear();
+                self.build_status = Some(BuildStatus::NotRun);
+                self.last_code_hash = None;
+            }
+        }
+    }
+
+    /// Announces how the opt IR changed from `previous_opt_ir` and, with
+    /// inline diffs on, starts marking the changes. The build diffed against
+    /// the opt IR shown when it started, which is rediffed only if another
+    /// build was shown since.
+    fn show_new_results(&mut self, previous_opt_ir: String) {
+        self.show_ir_changelog(&previous_opt_ir);
+        if self.inline_opt_diff && !previous_opt_ir.is_empty() && previous_opt_ir != self.opt_ir {
+            if self.prev_opt_ir != previous_opt_ir {
+                self.opt_ir_diff = diff_lines(&previous_opt_ir, &self.opt_ir);
+                self.prev_opt_ir = previous_opt_ir;
+            }
+            self.opt_ir_changed_at = Some(std::time::Instant::now());
+        }
+    }
+
+    /// Whether a build is waiting to be shown in manual apply mode.
+    pub(crate) fn has_staged_results(&self) -> bool {
+        self.staged_results.is_some()
+    }
+
+    /// Shows the build staged in manual apply mode, if any.
+    pub(crate) fn apply_staged_results(&mut self) {
+        let Some(mut staged) = self.staged_results.take() else {
+            return;
+        };
+        let previous_opt_ir = self.opt_ir.clone();
+        self.swap_results(&mut staged);
+        self.show_new_results(previous_opt_ir);
+        self.reveal_error_span();
+    }
+
+    fn swap_results(&mut self, results: &mut BuildResults) {
+        std::mem::swap(&mut self.unopt_ir, &mut results.unopt_ir);
+        std::mem::swap(&mut self.opt_ir, &mut results.opt_ir);
+        std::mem::swap(&mut self.delay_info, &mut results.delay_info);
+        std::mem::swap(&mut self.delay_summary, &mut results.delay_summary);
+        std::mem::swap(&mut self.opt_stats, &mut results.opt_stats);
+        std::mem::swap(&mut self.optimizer_diff, &mut results.optimizer_diff);
+        std::mem::swap(&mut self.prev_delay_info, &mut results.prev_delay_info);
+        std::mem::swap(&mut self.delay_diff, &mut results.delay_diff);
+        std::mem::swap(&mut self.prev_opt_ir, &mut results.prev_opt_ir);
+        std::mem::swap(&mut self.opt_ir_diff, &mut results.opt_ir_diff);
+        std::mem::swap(&mut self.compare_opt_ir, &mut results.compare_opt_ir);
+        std::mem::swap(&mut self.verilog, &mut results.verilog);
+        std::mem::swap(&mut self.pipeline_schedule, &mut results.pipeline_schedule);
+        std::mem::swap(&mut self.equivalence, &mut results.equivalence);
+        std::mem::swap(&mut self.error_message, &mut results.error_message);
+        std::mem::swap(&mut self.error_span, &mut results.error_span);
+        std::mem::swap(&mut self.stage_errors, &mut results.stage_errors);
+        std::mem::swap(&mut self.selected_error, &mut results.selected_error);
+        std::mem::swap(&mut self.warnings, &mut results.warnings);
+        std::mem::swap(&mut self.build_status, &mut results.build_status);
+        std::mem::swap(&mut self.interpreter_ok, &mut results.interpreter_ok);
+        std::mem::swap(&mut self.test_output, &mut results.test_output);
+        std::mem::swap(&mut self.stage_outputs, &mut results.stage_outputs);
+        std::mem::swap(&mut self.hook_log, &mut results.hook_log);
+    }
+
+    /// Rebuilds every artifact for the current source and selected entry.
+    /// Any build staged in manual apply mode is superseded.
+    pub fn check_and_run_conversion(&mut self) {
+        self.rebuild(BuildPurpose::Show);
+    }
+
+    /// Builds the current source in a copy of the build state, inline or
+    /// on the build worker, and applies the results for `purpose` when done.
+    /// A background build still queued or running is superseded: it stops
+    /// before its next tool run and its results are dropped.
+    fn rebuild(&mut self, purpose: BuildPurpose) {
+        self.staged_results = None;
+        // Editors that truncate before writing can briefly leave the file
+        // empty; wait for content instead of flashing a toolchain error.
+        self.last_code_hash = Some(fnv1a_hash(&self.code));
+        self.file_empty = self.code.trim().is_empty();
+        if self.file_empty {
+            self.cancel_build();
+            return;
+        }
+        let builder = self.builder();
+        self.dispatch(builder, purpose);
+    }
+
+    /// Runs `builder`'s job inline, or on the build worker, superseding any
+    /// background build, and applies the results for `purpose` when done.
+    fn dispatch(&mut self, mut builder: App, purpose: BuildPurpose) {
+        if !self.background_builds {
+            builder.run_job();
+            self.apply_build(builder, purpose);
+            return;
+        }
+        let generation = self.build_generation.fetch_add(1, Ordering::SeqCst) + 1;
+        builder.build_id = Some(generation);
+        let job = builder.job;
+        let worker = self.build_worker.get_or_insert_with(BuildWorker::start);
+        // A worker that died is noticed, and replaced, by `poll_build`.
+        let _ = worker.jobs.send(builder);
+        self.build = Some(BackgroundBuild {
+            generation,
+            purpose,
+            job,
+            started_at: Instant::now(),
+        });
+    }
+
+    /// Abandons any background build.
+    fn cancel_build(&mut self) {
+        self.build = None;
+        self.build_generation.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Whether this is a background build's copy that a newer build has
+    /// replaced.
+    fn is_superseded(&self) -> bool {
+        self.build_id
+            .is_some_and(|id| id != self.build_generation.load(Ordering::SeqCst))
+    }
+
+    /// A fresh `App` with this one's build settings and inputs, for running
+    /// a build without touching what is displayed.
+    fn builder(&self) -> App {
+        let mut builder = App::with_runner(self.tool_runner.clone());
+        builder.build_generation = Arc::clone(&self.build_generation);
+        builder.code = self.code.clone();
+        builder.file_path = self.file_path.clone();
+        builder.dslx_stdlib_path = self.dslx_stdlib_path.clone();
+        builder.tops_file = self.tops_file.clone();
+        builder.pinned_top = self.pinned_top.clone();
+        builder.jit_compare = self.jit_compare;
+        builder.compare_mismatch_non_fatal = self.compare_mismatch_non_fatal;
+        builder.test_filter = self.test_filter.clone();
+        builder.pre_build_hook = self.pre_build_hook.clone();
+        builder.post_build_hook = self.post_build_hook.clone();
+        builder.on_build = self.on_build.clone();
+        builder.delay_model = self.delay_model.clone();
+        builder.pipeline_stages = self.pipeline_stages;
+        builder.clock_period_ps = self.clock_period_ps;
+        builder.compare_entry = self.compare_entry.clone();
+        builder.entry_points = self.entry_points.clone();
+        builder.selected_entry = self.selected_entry;
+        // The previous build's outputs, for entry discovery and the delay
+        // diff.
+        builder.unopt_ir = self.unopt_ir.clone();
+        builder.delay_info = self.delay_info.clone();
+        builder.delay_summary = self.delay_summary.clone();
+        if self.inline_opt_diff {
+            builder.prev_opt_ir = self.opt_ir.clone();
+        }
+        builder.written_artifacts = self.written_artifacts.clone();
+        builder.backup_max_entries = self.backup_max_entries;
+        builder.backup_max_age = self.backup_max_age;
+        builder
+    }
+
+    /// Takes over the results and entry state of a finished build, or the
+    /// outputs of a finished rerun.
+    fn apply_build(&mut self, mut built: App, purpose: BuildPurpose) {
+        self.written_artifacts
+            .extend(built.written_artifacts.drain());
+        if built.job != BuildJob::Full {
+            self.apply_rerun(built);
+            return;
+        }
+        self.entry_points = std::mem::take(&mut built.entry_points);
+        self.entry_signatures = std::mem::take(&mut built.entry_signatures);
+        self.selected_entry = built.selected_entry;
+        self.compare_entry = built.compare_entry.take();
+        self.last_build_at = built.last_build_at;
+        self.last_build_duration = built.last_build_duration;
+        if built.on_build_rx.is_some() {
+            self.on_build_rx = built.on_build_rx.take();
+            self.on_build_status = built.on_build_status;
+        }
+        if let Some(entry) = self.entry_points.get(self.selected_entry) {
+            self.entry_status
+                .insert(entry.clone(), built.build_succeeded());
+        }
+        let mut results = BuildResults::default();
+        built.swap_results(&mut results);
+        match purpose {
+            BuildPurpose::Show => self.swap_results(&mut results),
+            BuildPurpose::Announce => {
+                self.swap_results(&mut results);
+                self.show_new_results(results.opt_ir);
+            }
+            BuildPurpose::Stage => {
+                self.staged_results = Some(results);
+                return;
+            }
+        }
+        self.reveal_error_span();
+    }
+
+    /// Applies the newest background build's results once it has finished,
+    /// dropping any that finished after being superseded.
+    pub(crate) fn poll_build(&mut self) {
+        let Some(worker) = &self.build_worker else {
+            return;
+        };
+        loop {
+            match worker.results.try_recv() {
+                Ok(mut built) => {
+                    let Some(build) = self.build.take_if(|b| built.build_id == Some(b.generation))
+                    else {
+                        // Its results are stale, but the artifacts it wrote
+                        // are still ours.
+                        self.written_artifacts
+                            .extend(built.written_artifacts.drain());
+                        continue;
+                    };
+                    self.apply_build(built, build.purpose);
+                    return;
+                }
+                Err(TryRecvError::Empty) => return,
+                Err(TryRecvError::Disconnected) => {
+                    self.build_worker = None;
+                    if self.build.take().is_some() {
+                        self.error_message = Some(String::from("The build stopped unexpectedly"));
+                        self.stage_errors.clear();
+                        self.build_status = Some(BuildStatus::NotRun);
+                    }
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Whether a background build is running.
+    pub(crate) fn is_building(&self) -> bool {
+        self.build.is_some()
+    }
+}
+
+/// Runs the builds sent on `jobs` one at a time and sends each back on
+/// `finished`. Of several builds queued at once only the newest runs.
+fn run_build_worker(jobs: Receiver<App>, finished: Sender<App>) {
+    while let Ok(mut builder) = jobs.recv() {
+        while let Ok(newer) = jobs.try_recv() {
+            builder = newer;
+        }
+        if builder.is_superseded() {
+            continue;
+        }
+        builder.run_job();
+        if finished.send(builder).is_err() {
+            return;
+        }
+    }
+}
+
+/// A warning for inputs without the `.x` extension, also printed to stderr.
+fn extension_warning(path: &Path) -> Option<String> {
+    if path.extension().and_then(|e| e.to_str()) == Some("x") {
+        return None;
+    }
+    let msg = format!("{} does not have a .x extension", path.display());
+    eprintln!("warning: {}", msg);
+    Some(msg)
+}
+
+/// `path` made absolute against the current directory, or unchanged if that
+/// fails.
+fn absolute_path(path: &str) -> String {
+    std::path::absolute(path)
+        .map_or_else(|_| path.to_string(), |p| p.to_string_lossy().into_owned())
+}
+
+/// `age` as a compact "3s", "2m" or "1h".
+fn format_age(age: Duration) -> String {
+    match age.as_secs() {
+        secs @ 0..=59 => format!("{}s", secs),
+        secs @ 60..=3599 => format!("{}m", secs / 60),
+        secs => format!("{}h", secs / 3600),
+    }
+}
+
+/// The scroll offset that shows `line` with up to `margin` lines of context
+/// on either side in a pane `viewport` lines tall, moving `scroll` only as far
+/// as needed, like an editor's `scrolloff`.
+pub(crate) fn scroll_into_view(
+    scroll: usize,
+    line: usize,
+    viewport: usize,
+    margin: usize,
+) -> usize {
+    let viewport = viewport.max(1);
+    let margin = margin.min((viewport - 1) / 2);
+    if line < scroll + margin {
+        line.saturating_sub(margin)
+    } else if line + margin >= scroll + viewport {
+        (line + margin + 1).saturating_sub(viewport)
+    } else {
+        scroll
+    }
+}
+
+/// Recognizes combinational cycles and non-synthesizable constructs in tool
+/// output. Cycle diagnostics name the IR nodes involved and, when `ir`
+/// defines them, the functions they belong to.
+fn explain_tool_error(stderr: &str, ir: &str) -> Option<String> {
+    let lower = stderr.to_lowercase();
+    let is_cycle = [
+        "cycle detected",
+        "contains a cycle",
+        "combinational cycle",
+        "combinational loop",
+    ]
+    .iter()
+    .any(|pattern| lower.contains(pattern));
+    if is_cycle {
+        let node_re = Regex::new(r"\b[A-Za-z_]\w*\.\d+\b").unwrap();
+        let mut nodes: Vec<&str> = Vec::new();
+        for m in node_re.find_iter(stderr) {
+            if !nodes.contains(&m.as_str()) {
+                nodes.push(m.as_str());
+            }
+        }
+        if nodes.is_empty() {
+            return Some(String::from("Combinational loop detected"));
+        }
+        let functions: Vec<String> = ir_nodes(ir)
+            .into_iter()
+            .filter(|(_, fn_nodes)| fn_nodes.iter().any(|n| nodes.contains(&n.name.as_str())))
+            .map(|(function, _)| function)
+            .collect();
+        let mut message = format!(
+            "Combinational loop detected involving nodes {}",
+            nodes.join(", ")
+        );
+        if !functions.is_empty() {
+            message.push_str(&format!(" (in {})", functions.join(", ")));
+        }
+        return Some(message);
+    }
+    if lower.contains("not synthesizable")
+        || lower.contains("non-synthesizable")
+        || lower.contains("cannot be converted to ir")
+    {
+        return Some(String::from(
+            "Design is not synthesizable: it uses a construct the IR converter cannot lower",
+        ));
+    }
+    None
+}
+
+/// `bytes` as text, truncated to `STAGE_OUTPUT_LIMIT` with a marker.
+fn bounded_lossy(bytes: &[u8]) -> String {
+    if bytes.len() <= STAGE_OUTPUT_LIMIT {
+        return String::from_utf8_lossy(bytes).into_owned();
+    }
+    format!(
+        "{}\n[ truncated {} bytes ]",
+        String::from_utf8_lossy(&bytes[..STAGE_OUTPUT_LIMIT]),
+        bytes.len() - STAGE_OUTPUT_LIMIT
+    )
+}
+
+/// The short name a tool's timing is shown under: `ir_converter_main` is
+/// `irconv`, `delay_info_main` is `delay`, and so on.
+fn stage_short_name(tool: &str) -> String {
+    match tool {
+        "ir_converter_main" => String::from("irconv"),
+        "opt_main" => String::from("opt"),
+        "delay_info_main" => String::from("delay"),
+        "dslx_interpreter_main" => String::from("interp"),
+        CODEGEN_TOOL => String::from("codegen"),
+        EQUIVALENCE_TOOL => String::from("equiv"),
+        other => other.trim_end_matches("_main").to_string(),
+    }
+}
+
+/// 64-bit FNV-1a hash, used to detect content-identical file changes.
+pub(crate) fn fnv1a_hash(text: &str) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+    text.bytes().fold(OFFSET_BASIS, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(PRIME)
+    })
+}
+
+/// How far a build got through the pipeline, for the status banner.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum BuildStatus {
+    // Stopped before IR conversion: the pre-build hook failed, required
+    // binaries are missing or the build itself broke down.
+    NotRun,
+    ConversionFailed,
+    // The IR converted, but optimization (or selecting `--top`) failed.
+    ConversionOk,
+    // The IR optimized, but delay analysis failed.
+    OptOk,
+    // Every artifact was generated; the tests passed or failed.
+    TestsPassed,
+    TestsFailed,
+    // Every artifact was generated, and there is no interpreter to run the
+    // tests.
+    TestsSkipped,
+}
+
+/// Outcome of `check_ir_equivalence_main` on the last build's unopt and opt
+/// IR.
+#[derive(Clone, Default)]
+pub(crate) enum Equivalence {
+    // Not checked since the last build, either because it stopped before
+    // there was opt IR to check or because no one asked yet.
+    #[default]
+    NotRun,
+    Unavailable,
+    // Whether the tool proved the IRs equivalent, and its output, which
+    // holds the counterexample when they are not.
+    Checked {
+        equivalent: bool,
+        report: String,
+    },
+}
+
+/// What the optimizer changed: the unopt IR diffed against the opt IR,
+/// computed once per build. Functions inlined or eliminated entirely show up
+/// as removed lines.
+#[derive(Clone, Default)]
+pub(crate) enum OptimizerDiff {
+    // The build stopped before there was opt IR to compare.
+    #[default]
+    NotRun,
+    // Too many lines differ to diff them within the memory limit.
+    TooLarge {
+        unopt_lines: usize,
+        opt_lines: usize,
+    },
+    Lines(Vec<DiffLine>),
+}
+
+impl OptimizerDiff {
+    fn of(unopt_ir: &str, opt_ir: &str) -> Self {
+        match unified_diff_lines(unopt_ir, opt_ir) {
+            Some(lines) => OptimizerDiff::Lines(lines),
+            None => OptimizerDiff::TooLarge {
+                unopt_lines: unopt_ir.lines().count(),
+                opt_lines: opt_ir.lines().count(),
+            },
+        }
+    }
+
+    /// The line shown instead of the diff when there is none to show.
+    pub(crate) fn placeholder(&self) -> Option<String> {
+        match self {
+            OptimizerDiff::NotRun => Some(String::from("[ no optimized IR to compare ]")),
+            OptimizerDiff::TooLarge {
+                unopt_lines,
+                opt_lines,
+            } => Some(format!(
+                "[ diff too large: {} unopt IR lines against {} opt IR lines ]",
+                unopt_lines, opt_lines
+            )),
+            OptimizerDiff::Lines(_) => None,
+        }
+    }
+
+    /// The tab text: the diff lines with their markers, or the placeholder.
+    fn text(&self) -> String {
+        match self {
+            OptimizerDiff::Lines(lines) => lines
+                .iter()
+                .map(DiffLine::to_text)
+                .collect::<Vec<_>>()
+                .join("\n"),
+            _ => self.placeholder().unwrap_or_default(),
+        }
+    }
+}
+
+impl Equivalence {
+    /// The tab text: a verdict line followed by the tool's output.
+    fn summary(&self) -> String {
+        match self {
+            Equivalence::NotRun => {
+                String::from("[ not run; checked while this tab is shown, or with :equiv ]")
+            }
+            Equivalence::Unavailable => {
+                format!("[ {} not found in XLSYNTH_TOOLS ]", EQUIVALENCE_TOOL)
+            }
+            Equivalence::Checked { equivalent, report } => format!(
+                "{}\n\n{}",
+                if *equivalent {
+                    "✓ opt IR is equivalent to unopt IR"
+                } else {
+                    "✗ opt IR is NOT equivalent to unopt IR"
+                },
+                report.trim_end()
+            ),
+        }
+    }
+}
+
+/// State of the `--on-build` command from the most recent successful build.
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum OnBuildStatus {
+    Running,
+    // Exit code, or `None` if it could not start or was killed by a signal.
+    Exited(Option<i32>),
+}
+
+/// Runs a hook through `sh -c`, returning whether it succeeded and its
+/// stdout followed by stderr.
+fn run_hook(command: &str, env: &[(&str, &str)]) -> (bool, String) {
+    match Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .envs(env.iter().copied())
+        .output()
+    {
+        Ok(output) => (
+            output.status.success(),
+            format!(
+                "{}{}",
+                String::from_utf8_lossy(&output.stdout),
+                String::from_utf8_lossy(&output.stderr)
+            ),
+        ),
+        Err(e) => (false, format!("failed to run hook: {}\n", e)),
+    }
+}
+
+/// Number of tests and quickchecks the interpreter reports having run, from
+/// its "N test(s) ran" summary; output without a summary or any `RUN` line
+/// means none ran.
+fn tests_run(output: &str) -> usize {
+    let summary_re = Regex::new(r"(\d+) test\(s\) ran").unwrap();
+    match summary_re.captures(output) {
+        Some(cap) => cap[1].parse().unwrap_or(0),
+        None => output.matches("[ RUN").count(),
+    }
+}
+
+/// Tests run and tests failed according to the interpreter's "N test(s) ran;
+/// M failed" summary, or counted from its `RUN` and `FAILED` lines.
+fn test_counts(output: &str) -> (usize, usize) {
+    let summary_re = Regex::new(r"(\d+) test\(s\) ran; (\d+) failed").unwrap();
+    match summary_re.captures(output) {
+        Some(cap) => (cap[1].parse().unwrap_or(0), cap[2].parse().unwrap_or(0)),
+        None => (
+            output.matches("[ RUN").count(),
+            output.matches("FAILED ]").count(),
+        ),
+    }
+}
+
+/// Interpreter output with each passing test's `RUN`..`OK` block dropped and
+/// a single count line in place of the first one; failing tests and lines
+/// outside any test are kept as they are.
+pub(crate) fn failures_only(output: &str) -> String {
+    let mut kept: Vec<&str> = Vec::new();
+    let mut block: Vec<&str> = Vec::new();
+    let mut passed = 0;
+    let mut count_at = None;
+    for line in output.lines() {
+        if line.starts_with("[ RUN") {
+            kept.append(&mut block);
+            block.push(line);
+        } else if block.is_empty() {
+            kept.push(line);
+        } else if line.trim_end() == "[            OK ]" {
+            block.clear();
+            passed += 1;
+            count_at.get_or_insert(kept.len());
+        } else {
+            block.push(line);
+            if line.contains("FAILED ]") {
+                kept.append(&mut block);
+            }
+        }
+    }
+    kept.append(&mut block);
+    let mut lines: Vec<String> = kept.into_iter().map(str::to_string).collect();
+    if let Some(at) = count_at {
+        lines.insert(at, format!("[ {} passing test(s) hidden ]", passed));
+    }
+    lines.join("\n")
+}
+
+/// Whether interpreter stderr describes a disagreement between the interpreter
+/// and the JIT rather than an ordinary test failure.
+fn is_compare_mismatch(stderr: &str) -> bool {
+    let re = Regex::new(r"(?i)mismatch.*\bjit\b|\bjit\b.*mismatch").unwrap();
+    re.is_match(stderr)
+}
+
+/// Reads the DSLX source at `path`, turning the common failure modes into a
+/// message that names the file instead of a bare io error.
+fn read_dslx_source(path: &str) -> Result<String, String> {
+    let bytes = fs::read(path).map_err(|e| format!("cannot read input file {}: {}", path, e))?;
+    String::from_utf8(bytes).map_err(|e| {
+        format!(
+            "input file is not valid UTF-8 text: {} (invalid byte at offset {})",
+            path,
+            e.utf8_error().valid_up_to()
+        )
+    })
+}
+
+/// [`GENERATED_MARKER`] with a hash of an artifact's `contents`.
+fn artifact_marker(contents: &str) -> String {
+    format!("{} (fnv1a {:016x})", GENERATED_MARKER, fnv1a_hash(contents))
+}
+
+/// `marker` as a comment heading the artifact at `path`. `None` for formats
+/// without comments, the delay table exports, whose marker goes in a
+/// `.generated` file beside them instead.
+fn artifact_header(path: &Path, marker: &str) -> Option<String> {
+    match path.extension()?.to_str()? {
+        "ir" | "dot" | "x" => Some(format!("// {}", marker)),
+        "html" => Some(format!("<!-- {} -->", marker)),
+        _ => None,
+    }
+}
+
+/// Where the marker of the artifact at `path` goes when it cannot hold one.
+fn marker_path_for(path: &Path) -> PathBuf {
+    PathBuf::from(format!("{}.generated", path.display()))
+}
+
+/// Whether `existing`, the contents of the artifact at `path`, was written by
+/// an earlier session and not edited since: its marker matches its contents.
+fn is_generated_artifact(path: &Path, existing: &str) -> bool {
+    if artifact_header(path, "").is_none() {
+        return fs::read_to_string(marker_path_for(path))
+            .is_ok_and(|marker| marker == artifact_marker(existing));
+    }
+    existing.split_once('\n').is_some_and(|(header, contents)| {
+        artifact_header(path, &artifact_marker(contents)).as_deref() == Some(header)
+    })
+}
+
+/// Picks a backup name for `path` that does not clobber an earlier backup:
+/// `foo.ir.bak`, then `foo.ir.bak.1`, `foo.ir.bak.2`, ...
+fn backup_path_for(path: &Path) -> PathBuf {
+    let base = format!("{}.bak", path.display());
+    let mut candidate = PathBuf::from(&base);
+    let mut n = 1;
+    while candidate.exists() {
+        candidate = PathBuf::from(format!("{}.{}", base, n));
+        n += 1;
+    }
+    candidate
+}
@@ -0,0 +1,245 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Headless single-build mode (`--json`, `--once`, `--once-json-pretty`
+//! and `--export-dir`).
+
+use crate::app::App;
+use crate::config::Config;
+use crate::ir::ir_op_counts;
+use crate::tools::{ToolRunner, XlsynthToolRunner};
+use regex::Regex;
+use serde::Serialize;
+use std::error::Error;
+use std::time::{Duration, Instant};
+
+/// Version of the `--json` report layout; bump when fields change meaning.
+const JSON_SCHEMA_VERSION: u32 = 1;
+
+/// Field names accepted by `--json-fields`, in report order.
+const JSON_FIELDS: &[&str] = &[
+    "file",
+    "package",
+    "entries",
+    "errors",
+    "warnings",
+    "build_duration_ms",
+    "schema_version",
+    "timestamp",
+];
+
+#[derive(Clone, Debug, Serialize)]
+#[non_exhaustive]
+pub struct EntryResult {
+    pub name: String,
+    pub op_count: usize,
+}
+
+/// Machine-readable summary of a single build, printed by `--json`.
+#[derive(Clone, Debug, Serialize)]
+#[non_exhaustive]
+pub struct BuildResult {
+    pub file: String,
+    pub package: Option<String>,
+    pub entries: Vec<EntryResult>,
+    pub errors: Vec<String>,
+    pub warnings: Vec<String>,
+    pub build_duration_ms: u128,
+    pub schema_version: u32,
+    pub timestamp: String,
+}
+
+impl BuildResult {
+    pub fn from_app(app: &App, build_duration: Duration) -> Self {
+        let package_re = Regex::new(r"(?m)^package (\w+)").unwrap();
+        let op_counts = ir_op_counts(&app.unopt_ir);
+        Self {
+            file: app.file_path.clone().unwrap_or_default(),
+            package: package_re
+                .captures(&app.unopt_ir)
+                .map(|cap| cap[1].to_string()),
+            entries: app
+                .entry_points
+                .iter()
+                .map(|name| EntryResult {
+                    name: name.clone(),
+                    op_count: op_counts.get(name).copied().unwrap_or(0),
+                })
+                .collect(),
+            errors: if app.file_empty {
+                vec!["file is empty".to_string()]
+            } else if !app.stage_errors.is_empty() {
+                app.stage_errors
+                    .iter()
+                    .map(|(stage, error)| format!("{}: {}", stage, error))
+                    .collect()
+            } else {
+                app.error_message.iter().cloned().collect()
+            },
+            warnings: app
+                .input_warning
+                .iter()
+                .chain(app.warnings.iter())
+                .cloned()
+                .collect(),
+            build_duration_ms: build_duration.as_millis(),
+            schema_version: JSON_SCHEMA_VERSION,
+            timestamp: chrono::Local::now().to_rfc3339(),
+        }
+    }
+}
+
+/// Batch-mode exit codes. 2 is left to clap for command-line usage errors.
+const EXIT_OK: i32 = 0;
+const EXIT_BUILD_FAILED: i32 = 1;
+const EXIT_TESTS_FAILED: i32 = 3;
+
+/// Exit status for a headless build. Test and JIT-compare results only count
+/// when `require_tests_pass` is set; otherwise success means the artifacts
+/// were generated.
+fn batch_exit_code(app: &App, require_tests_pass: bool) -> i32 {
+    let build_failed = app.file_empty
+        || (app.error_message.is_some() && app.interpreter_ok != Some(false))
+        || app
+            .stage_errors
+            .iter()
+            .any(|(stage, _)| stage != "dslx_interpreter_main");
+    if build_failed {
+        EXIT_BUILD_FAILED
+    } else if require_tests_pass && app.interpreter_ok != Some(true) {
+        if app.interpreter_ok.is_none() {
+            eprintln!("error: --require-tests-pass given but dslx_interpreter_main did not run");
+        }
+        EXIT_TESTS_FAILED
+    } else {
+        EXIT_OK
+    }
+}
+
+/// Parses a `--json-fields` list, rejecting names not in `JSON_FIELDS`.
+pub(crate) fn parse_json_fields(spec: &str) -> Result<Vec<String>, String> {
+    let mut fields = Vec::new();
+    for field in spec.split(',').map(str::trim).filter(|f| !f.is_empty()) {
+        if !JSON_FIELDS.contains(&field) {
+            return Err(format!(
+                "unknown --json-fields entry '{}'; valid fields are: {}",
+                field,
+                JSON_FIELDS.join(", ")
+            ));
+        }
+        fields.push(field.to_string());
+    }
+    Ok(fields)
+}
+
+/// Serializes the full report, then keeps only `fields` (all when `None`).
+fn build_result_json(result: &BuildResult, fields: Option<&[String]>) -> serde_json::Value {
+    let mut value = serde_json::to_value(result).expect("BuildResult is always serializable");
+    if let (Some(fields), serde_json::Value::Object(map)) = (fields, &mut value) {
+        map.retain(|key, _| fields.iter().any(|f| f == key));
+    }
+    value
+}
+
+/// Plain-text rendering of `result` for the terminal, alongside the JSON
+/// report written by `--once-json-pretty` or on its own for `--export-dir`.
+fn human_summary(result: &BuildResult) -> String {
+    let mut summary = result.file.clone();
+    if let Some(package) = &result.package {
+        summary.push_str(&format!(" (package {})", package));
+    }
+    summary.push_str(&format!(
+        ": {} in {} ms\n",
+        if result.errors.is_empty() {
+            "built"
+        } else {
+            "build failed"
+        },
+        result.build_duration_ms
+    ));
+    for entry in &result.entries {
+        summary.push_str(&format!("  {} ({} ops)\n", entry.name, entry.op_count));
+    }
+    for warning in &result.warnings {
+        summary.push_str(&format!("warning: {}\n", warning));
+    }
+    for error in &result.errors {
+        summary.push_str(&format!("error: {}\n", error));
+    }
+    summary
+}
+
+/// Runs a single build for `config` with the xlsynth tools named by
+/// `XLSYNTH_TOOLS`, reports it as `config` asks and returns the exit code.
+pub fn run_batch(config: Config) -> Result<i32, Box<dyn Error>> {
+    let runner = XlsynthToolRunner::from_env_with_tool_paths(&config.tool_paths)?
+        .with_workdir(config.tool_workdir());
+    run_batch_with_runner(config, Box::new(runner))
+}
+
+/// Like [`run_batch`], invoking tools through `tool_runner`.
+pub fn run_batch_with_runner(
+    config: Config,
+    tool_runner: Box<dyn ToolRunner>,
+) -> Result<i32, Box<dyn Error>> {
+    let mut app = App::from_config(&config, tool_runner)?;
+    let start = Instant::now();
+    app.check_and_run_conversion();
+    app.poll_on_build(true);
+    let result = BuildResult::from_app(&app, start.elapsed());
+    let json = build_result_json(&result, config.json_fields.as_deref());
+    match &config.json_pretty_file {
+        Some(path) => {
+            let pretty = serde_json::to_string_pretty(&json)?;
+            std::fs::write(path, pretty + "\n")?;
+            print!("{}", human_summary(&result));
+            println!("JSON report written to {}", path.display());
+        }
+        None if config.json => println!("{}", json),
+        None if config.once => {}
+        None => print!("{}", human_summary(&result)),
+    }
+    if let Some(dir) = &config.export_dir {
+        let written = app.export_artifacts(dir)?;
+        // Keeps stdout pure JSON for `--json`.
+        let note = format!("Exported {} files to {}", written.len(), dir.display());
+        if config.json {
+            eprintln!("{}", note);
+        } else {
+            println!("{}", note);
+        }
+    }
+    let exit_code = batch_exit_code(&app, config.require_tests_pass);
+    if config.once {
+        return Ok(emit_artifact(&app, &result, &config.emit, exit_code));
+    }
+    Ok(exit_code)
+}
+
+/// For `--once`: prints the build's errors to stderr and the `emit`
+/// artifact, if the build produced it, to stdout. Returns `exit_code`, or
+/// a failure if the artifact is missing although the build succeeded.
+fn emit_artifact(app: &App, result: &BuildResult, emit: &str, exit_code: i32) -> i32 {
+    for error in &result.errors {
+        eprintln!("error: {}", error);
+    }
+    let artifact = match emit {
+        "unopt-ir" => app.unopt_ir(),
+        "opt-ir" => app.opt_ir(),
+        "delay" => app.delay_info(),
+        _ => app.verilog.as_str(),
+    };
+    // Placeholders such as "[ codegen_main not found ... ]" are not output.
+    if artifact.is_empty() || artifact.starts_with("[ ") {
+        if exit_code == EXIT_OK {
+            let reason = artifact.trim_matches(|c| c == '[' || c == ']' || c == ' ');
+            eprintln!("error: no {} produced: {}", emit, reason);
+            return EXIT_BUILD_FAILED;
+        }
+        return exit_code;
+    }
+    print!("{}", artifact);
+    if !artifact.ends_with('\n') {
+        println!();
+    }
+    exit_code
+}
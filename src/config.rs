@@ -0,0 +1,650 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Settings for a watch session, usually parsed from the command line.
+
+use crate::app::{Focus, DELAY_MODELS, TABS};
+use crate::batch::parse_json_fields;
+use crate::config_file::load_config_file;
+use crate::theme::Theme;
+use chrono::format::{Item, StrftimeItems};
+use clap::parser::ValueSource;
+use clap::{Arg, ArgGroup, Command as ClapCommand};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Default `strftime` format of the "updated at" time.
+pub(crate) const DEFAULT_TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// Artifacts `--emit` selects between; the opt IR by default.
+pub(crate) const EMIT_ARTIFACTS: [&str; 4] = ["unopt-ir", "opt-ir", "delay", "verilog"];
+
+/// Flags overriding one tool binary each, with the tool they replace.
+const TOOL_PATH_FLAGS: [(&str, &str); 4] = [
+    ("ir-converter-path", "ir_converter_main"),
+    ("opt-main-path", "opt_main"),
+    ("delay-info-path", "delay_info_main"),
+    ("interpreter-path", "dslx_interpreter_main"),
+];
+
+/// Everything needed to start a session. Construct with [`Config::new`] and
+/// adjust the public fields, or parse the command line with
+/// [`Config::from_cli`].
+#[derive(Clone)]
+#[non_exhaustive]
+pub struct Config {
+    /// The DSLX file to watch, active at startup.
+    pub file: PathBuf,
+    /// Further DSLX files to watch, switched to with `[` and `]`.
+    pub extra_files: Vec<PathBuf>,
+    pub dslx_stdlib_path: Option<String>,
+    pub theme: Theme,
+    /// Interval for the polling watcher, used when native watching is
+    /// unavailable or `force_poll` is set.
+    pub poll_interval: Duration,
+    pub force_poll: bool,
+    /// Quiet period after a file change event before rebuilding, so the
+    /// bursts of events editors emit on save trigger a single build.
+    pub debounce: Duration,
+    pub large_file_warn_kb: usize,
+    /// Run one build and print a JSON report instead of starting the TUI.
+    pub json: bool,
+    /// Run one build and print the artifact `emit` names instead of
+    /// starting the TUI.
+    pub once: bool,
+    /// Artifact `once` prints: `unopt-ir`, `opt-ir`, `delay` or `verilog`.
+    pub emit: String,
+    /// Restricts the `json` report to these fields; all when `None`.
+    pub json_fields: Option<Vec<String>>,
+    /// Run one build, write a pretty-printed JSON report to this file and
+    /// print a human-readable summary instead of starting the TUI.
+    pub json_pretty_file: Option<PathBuf>,
+    pub require_tests_pass: bool,
+    /// Run one build and write its artifacts and a manifest to this
+    /// directory instead of starting the TUI; combines with `json`.
+    pub export_dir: Option<PathBuf>,
+    pub tops_file: Option<PathBuf>,
+    /// Entry to build on every rebuild, by IR or DSLX name; a build fails if
+    /// the IR has no such function.
+    pub top: Option<String>,
+    pub compare_mismatch_non_fatal: bool,
+    /// Run the interpreter without `--compare=jit`.
+    pub no_jit_compare: bool,
+    /// Shell command run before each build; a failure aborts the build.
+    pub pre_build_hook: Option<String>,
+    /// Shell command run after each build with `BUILD_RESULT=pass|fail`.
+    pub post_build_hook: Option<String>,
+    /// Shell command started in the background after each successful build,
+    /// with the artifact paths in its environment.
+    pub on_build: Option<String>,
+    /// Pane the arrow keys act on at startup; `f` switches it.
+    pub focus: Focus,
+    /// Caps the UI width, centering it on wider terminals.
+    pub max_width: Option<u16>,
+    /// Keep at most this many `.bak` backups per artifact.
+    pub backup_max_entries: Option<usize>,
+    /// Delete `.bak` backups older than this.
+    pub backup_max_age: Option<Duration>,
+    /// Run only the DSLX test with this name.
+    pub test_filter: Option<String>,
+    /// `strftime` format of the "updated at" time in the source pane title.
+    pub time_format: String,
+    /// Show the last update as an age ("3s ago") instead of a time.
+    pub relative_time: bool,
+    /// Lines kept visible above and below a search match or goto target.
+    pub scroll_off: usize,
+    /// Keep builds triggered by file changes out of view until applied.
+    pub manual_apply: bool,
+    /// Results tabs to show, in order, as (stage, label) pairs; all tabs
+    /// with their default labels when `None`. Stages are `unopt`, `opt`,
+    /// `delay`, `callgraph`, `log`, `stats`, `verify`, `verilog`,
+    /// `optdiff` and `equiv`.
+    pub tabs: Option<Vec<(String, String)>>,
+    /// Stage of the tab selected at startup; the first tab when `None`.
+    pub tab: Option<String>,
+    /// Directory the xlsynth tools run in; the watched file's directory
+    /// when `None`.
+    pub workdir: Option<PathBuf>,
+    /// Binaries to run instead of those in `XLSYNTH_TOOLS`, by tool name
+    /// (e.g. `opt_main`).
+    pub tool_paths: HashMap<String, PathBuf>,
+    /// `--delay_model` passed to `delay_info_main`.
+    pub delay_model: String,
+    /// Delay models the `D` key cycles through.
+    pub delay_models: Vec<String>,
+    /// `--pipeline_stages` passed to `codegen_main`; one stage when neither
+    /// this nor `clock_period_ps` is set.
+    pub pipeline_stages: Option<usize>,
+    /// `--clock_period_ps` passed to `codegen_main`.
+    pub clock_period_ps: Option<u64>,
+    /// Neither restore the view state of the last session on this file nor
+    /// save it on exit.
+    pub no_state: bool,
+}
+
+impl Config {
+    /// Defaults for watching `file`, matching the command-line defaults.
+    pub fn new(file: impl Into<PathBuf>) -> Self {
+        Self {
+            file: file.into(),
+            extra_files: Vec::new(),
+            dslx_stdlib_path: None,
+            theme: Theme::default_colors(),
+            poll_interval: Duration::from_millis(500),
+            force_poll: false,
+            debounce: Duration::from_millis(150),
+            large_file_warn_kb: 100,
+            json: false,
+            once: false,
+            emit: String::from(EMIT_ARTIFACTS[1]),
+            json_fields: None,
+            json_pretty_file: None,
+            require_tests_pass: false,
+            export_dir: None,
+            tops_file: None,
+            top: None,
+            compare_mismatch_non_fatal: false,
+            no_jit_compare: false,
+            pre_build_hook: None,
+            post_build_hook: None,
+            on_build: None,
+            focus: Focus::Entries,
+            max_width: None,
+            backup_max_entries: None,
+            backup_max_age: None,
+            test_filter: None,
+            time_format: String::from(DEFAULT_TIME_FORMAT),
+            relative_time: false,
+            scroll_off: 3,
+            manual_apply: false,
+            tabs: None,
+            tab: None,
+            workdir: None,
+            tool_paths: HashMap::new(),
+            delay_model: String::from(DELAY_MODELS[0]),
+            delay_models: DELAY_MODELS.map(String::from).to_vec(),
+            pipeline_stages: None,
+            clock_period_ps: None,
+            no_state: false,
+        }
+    }
+
+    /// The directory the tools run in: `workdir`, or else the directory of
+    /// the watched file.
+    pub(crate) fn tool_workdir(&self) -> PathBuf {
+        match (&self.workdir, self.file.parent()) {
+            (Some(workdir), _) => workdir.clone(),
+            (None, Some(dir)) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+            _ => PathBuf::from("."),
+        }
+    }
+
+    /// Parses the process command line, exiting with a usage error on invalid
+    /// arguments.
+    pub fn from_cli() -> Self {
+        let matches = ClapCommand::new("DSLX Playground")
+            .version("1.0")
+            .author("Author Name <email@example.com>")
+            .about("Watches a DSLX file and renders its IR")
+            .arg(
+                Arg::new("file")
+                    .short('f')
+                    .long("file")
+                    .value_name("FILE")
+                    .help("Sets the input file to watch; repeat to watch several")
+                    .action(clap::ArgAction::Append)
+                    .required(true),
+            )
+            .arg(
+                Arg::new("dslx_stdlib_path")
+                    .long("dslx_stdlib_path")
+                    .value_name("PATH")
+                    .help("Optional path to the DSLX standard library")
+                    .required(false),
+            )
+            .arg(
+                Arg::new("color_scheme")
+                    .long("color-scheme")
+                    .value_name("SCHEME")
+                    .help("Color scheme: default, ansi16 (basic 16-color palette) or none (monochrome)")
+                    .value_parser(["default", "ansi16", "none"])
+                    .default_value("default"),
+            )
+            .arg(
+                Arg::new("poll_ms")
+                    .long("poll-ms")
+                    .value_name("MS")
+                    .help("Polling interval when native file watching is unavailable")
+                    .value_parser(clap::value_parser!(u64))
+                    .default_value("500"),
+            )
+            .arg(
+                Arg::new("poll")
+                    .long("poll")
+                    .value_name("MS")
+                    .help("Shorthand for --force-poll, optionally with the --poll-ms interval")
+                    .value_parser(clap::value_parser!(u64))
+                    .num_args(0..=1)
+                    .require_equals(true)
+                    .default_missing_value("500")
+                    .conflicts_with_all(["force_poll", "poll_ms"]),
+            )
+            .arg(
+                Arg::new("debounce_ms")
+                    .long("debounce-ms")
+                    .value_name("MS")
+                    .help("Wait until file change events stop for MS milliseconds before rebuilding")
+                    .value_parser(clap::value_parser!(u64))
+                    .default_value("150"),
+            )
+            .arg(
+                Arg::new("force_poll")
+                    .long("force-poll")
+                    .help("Poll the file for changes instead of using native file watching")
+                    .action(clap::ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("large_file_warn_kb")
+                    .long("large-file-warn-kb")
+                    .value_name("N")
+                    .help("Show a large file badge for sources bigger than N KB")
+                    .value_parser(clap::value_parser!(usize))
+                    .default_value("100"),
+            )
+            .arg(
+                Arg::new("json")
+                    .long("json")
+                    .help("Run a single build without the TUI and print a JSON report")
+                    .action(clap::ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("once_json_pretty")
+                    .long("once-json-pretty")
+                    .value_name("FILE")
+                    .help("Run a single build without the TUI, write a pretty-printed JSON report to FILE and print a summary"),
+            )
+            .arg(
+                Arg::new("once")
+                    .long("once")
+                    .help("Run a single build without the TUI and print the --emit artifact to stdout")
+                    .action(clap::ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("emit")
+                    .long("emit")
+                    .value_name("ARTIFACT")
+                    .help("Artifact --once prints")
+                    .value_parser(EMIT_ARTIFACTS)
+                    .default_value(EMIT_ARTIFACTS[1])
+                    .requires("once"),
+            )
+            .group(ArgGroup::new("batch").args(["json", "once_json_pretty", "once"]))
+            .arg(
+                Arg::new("export_dir")
+                    .long("export-dir")
+                    .value_name("DIR")
+                    .help("Run a single build without the TUI and write its artifacts and a manifest to DIR"),
+            )
+            .arg(
+                Arg::new("json_fields")
+                    .long("json-fields")
+                    .value_name("FIELD,...")
+                    .help("Only emit these fields in the JSON report")
+                    .requires("batch")
+                    .conflicts_with("once"),
+            )
+            .arg(
+                Arg::new("require_tests_pass")
+                    .long("require-tests-pass")
+                    .help("In batch mode, also exit non-zero when tests or the JIT comparison fail")
+                    .action(clap::ArgAction::SetTrue)
+                    .requires("batch"),
+            )
+            .arg(
+                Arg::new("tops_file")
+                    .long("tops-file")
+                    .value_name("FILE")
+                    .help("File listing entry functions, one per line, used instead of discovery"),
+            )
+            .arg(
+                Arg::new("top")
+                    .long("top")
+                    .value_name("NAME")
+                    .help("Entry function to build, by IR or DSLX name, instead of the first one"),
+            )
+            .arg(
+                Arg::new("compare_mismatch_non_fatal")
+                    .long("compare_mismatch_non_fatal")
+                    .help("Report interpreter/JIT comparison mismatches as warnings instead of errors")
+                    .action(clap::ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("no_jit_compare")
+                    .long("no-jit-compare")
+                    .help("Run the DSLX interpreter without comparing against the JIT")
+                    .action(clap::ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("pre_build_hook")
+                    .long("pre-build-hook")
+                    .value_name("CMD")
+                    .help("Shell command run before each build; a non-zero exit aborts the build"),
+            )
+            .arg(
+                Arg::new("post_build_hook")
+                    .long("post-build-hook")
+                    .value_name("CMD")
+                    .help("Shell command run after each build, with BUILD_RESULT=pass|fail set"),
+            )
+            .arg(
+                Arg::new("on_build")
+                    .long("on-build")
+                    .value_name("CMD")
+                    .help("Shell command started in the background after each successful build"),
+            )
+            .arg(
+                Arg::new("focus")
+                    .long("focus")
+                    .value_name("PANE")
+                    .help("Initial focus: entries (arrow keys change entry) or results (arrow keys scroll)")
+                    .value_parser(["entries", "results"])
+                    .default_value("entries"),
+            )
+            .arg(
+                Arg::new("max_width")
+                    .long("max-width")
+                    .value_name("COLUMNS")
+                    .help("Maximum width of the UI; wider terminals center it")
+                    .value_parser(clap::value_parser!(u16).range(40..)),
+            )
+            .arg(
+                Arg::new("backup_max_entries")
+                    .long("backup-max-entries")
+                    .value_name("N")
+                    .help("Keep at most N .bak backups of each artifact, deleting the oldest")
+                    .value_parser(clap::builder::RangedU64ValueParser::<usize>::new().range(1..)),
+            )
+            .arg(
+                Arg::new("backup_max_age_days")
+                    .long("backup-max-age-days")
+                    .value_name("DAYS")
+                    .help("Delete .bak backups of artifacts older than DAYS days")
+                    .value_parser(clap::value_parser!(u64)),
+            )
+            .arg(
+                Arg::new("test")
+                    .long("test")
+                    .value_name("NAME")
+                    .help("Run only the DSLX test named NAME"),
+            )
+            .arg(
+                Arg::new("time_format")
+                    .long("time-format")
+                    .value_name("FMT")
+                    .help("strftime format of the \"updated at\" time")
+                    .value_parser(parse_time_format)
+                    .default_value(DEFAULT_TIME_FORMAT),
+            )
+            .arg(
+                Arg::new("relative_time")
+                    .long("relative-time")
+                    .help("Show how long ago the file was updated instead of the time")
+                    .action(clap::ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("scroll_off")
+                    .long("scroll-off")
+                    .value_name("LINES")
+                    .help("Context lines kept above and below search matches and goto targets")
+                    .value_parser(clap::value_parser!(usize))
+                    .default_value("3"),
+            )
+            .arg(
+                Arg::new("no_state")
+                    .long("no-state")
+                    .help("Don't restore or save the tab, entry and scroll state between sessions")
+                    .action(clap::ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("manual_apply")
+                    .long("manual-apply")
+                    .help("Rebuild on file changes but only show the results when a is pressed")
+                    .action(clap::ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("tabs")
+                    .long("tabs")
+                    .value_name("STAGE[=LABEL],...")
+                    .help("Results tabs to show, in order, optionally relabelled")
+                    .value_parser(parse_tabs),
+            )
+            .arg(
+                Arg::new("tab")
+                    .long("tab")
+                    .value_name("STAGE")
+                    .help("Results tab to select at startup, e.g. opt or delay"),
+            )
+            .arg(
+                Arg::new("workdir")
+                    .long("workdir")
+                    .value_name("DIR")
+                    .help("Directory to run the XLS tools in (default: the file's directory)"),
+            )
+            .args(TOOL_PATH_FLAGS.iter().map(|(flag, tool)| {
+                Arg::new(*flag)
+                    .long(*flag)
+                    .value_name("PATH")
+                    .help(format!("Run this binary instead of {} from XLSYNTH_TOOLS", tool))
+            }))
+            .arg(
+                Arg::new("delay_model")
+                    .long("delay-model")
+                    .value_name("MODEL")
+                    .alias("delay_model")
+                    .help("Delay model for delay_info_main, e.g. asap7, sky130 or unit")
+                    .default_value(DELAY_MODELS[0]),
+            )
+            .arg(
+                Arg::new("delay_models")
+                    .long("delay-models")
+                    .value_name("MODEL,...")
+                    .help("Delay models the D key cycles through (default: asap7,sky130,unit)")
+                    .value_parser(parse_delay_models),
+            )
+            .arg(
+                Arg::new("pipeline_stages")
+                    .long("pipeline-stages")
+                    .value_name("N")
+                    .alias("pipeline_stages")
+                    .help("Pipeline stages codegen_main schedules the Verilog into (default: 1)")
+                    .value_parser(clap::builder::RangedU64ValueParser::<usize>::new().range(1..)),
+            )
+            .arg(
+                Arg::new("clock_period_ps")
+                    .long("clock-period-ps")
+                    .value_name("PS")
+                    .alias("clock_period_ps")
+                    .help("Target clock period for codegen_main's pipeline schedule")
+                    .value_parser(clap::value_parser!(u64).range(1..)),
+            )
+            .get_matches();
+
+        let mut files = matches
+            .get_many::<String>("file")
+            .unwrap()
+            .map(PathBuf::from);
+        let file = files.next().unwrap();
+        let extra_files: Vec<PathBuf> = files.collect();
+        if !extra_files.is_empty()
+            && (matches.contains_id("batch") || matches.contains_id("export_dir"))
+        {
+            eprintln!(
+                "error: --json, --once, --once-json-pretty and --export-dir take a single --file"
+            );
+            std::process::exit(2);
+        }
+        // Flags given on the command line win over the configuration file,
+        // which wins over the flags' defaults.
+        let file_config = load_config_file().unwrap_or_else(|msg| {
+            eprintln!("error: {}", msg);
+            std::process::exit(2);
+        });
+        let file_config = file_config.unwrap_or_default();
+        let given = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+        let color_scheme = match &file_config.color_scheme {
+            Some(scheme) if !given("color_scheme") => scheme,
+            _ => matches.get_one::<String>("color_scheme").unwrap(),
+        };
+        let mut theme = Theme::from_name(color_scheme).unwrap_or_else(|| {
+            eprintln!("error: unknown color scheme '{}'", color_scheme);
+            std::process::exit(2);
+        });
+        for (element, color) in &file_config.colors {
+            if let Err(msg) = theme.set_color(element, color) {
+                eprintln!("error: [colors] {}", msg);
+                std::process::exit(2);
+            }
+        }
+        let debounce_ms = match file_config.debounce_ms {
+            Some(ms) if !given("debounce_ms") => ms,
+            _ => *matches.get_one::<u64>("debounce_ms").unwrap(),
+        };
+        let delay_model = match file_config.delay_model {
+            Some(model) if !given("delay_model") => model,
+            _ => matches.get_one::<String>("delay_model").unwrap().clone(),
+        };
+        let json_fields = match matches.get_one::<String>("json_fields") {
+            Some(spec) => match parse_json_fields(spec) {
+                Ok(fields) => Some(fields),
+                Err(msg) => {
+                    eprintln!("error: {}", msg);
+                    std::process::exit(2);
+                }
+            },
+            None => None,
+        };
+        Self {
+            file,
+            extra_files,
+            dslx_stdlib_path: matches
+                .get_one::<String>("dslx_stdlib_path")
+                .cloned()
+                .or(file_config.dslx_stdlib_path),
+            theme,
+            poll_interval: Duration::from_millis(
+                *matches
+                    .get_one::<u64>("poll")
+                    .or(matches.get_one::<u64>("poll_ms"))
+                    .unwrap(),
+            ),
+            force_poll: matches.get_flag("force_poll") || matches.contains_id("poll"),
+            debounce: Duration::from_millis(debounce_ms),
+            large_file_warn_kb: *matches.get_one::<usize>("large_file_warn_kb").unwrap(),
+            json: matches.get_flag("json"),
+            once: matches.get_flag("once"),
+            emit: matches.get_one::<String>("emit").unwrap().clone(),
+            json_fields,
+            json_pretty_file: matches
+                .get_one::<String>("once_json_pretty")
+                .map(PathBuf::from),
+            require_tests_pass: matches.get_flag("require_tests_pass"),
+            export_dir: matches.get_one::<String>("export_dir").map(PathBuf::from),
+            tops_file: matches.get_one::<String>("tops_file").map(PathBuf::from),
+            top: matches.get_one::<String>("top").cloned(),
+            compare_mismatch_non_fatal: matches.get_flag("compare_mismatch_non_fatal"),
+            no_jit_compare: matches.get_flag("no_jit_compare"),
+            pre_build_hook: matches.get_one::<String>("pre_build_hook").cloned(),
+            post_build_hook: matches.get_one::<String>("post_build_hook").cloned(),
+            on_build: matches.get_one::<String>("on_build").cloned(),
+            focus: match matches.get_one::<String>("focus").unwrap().as_str() {
+                "results" => Focus::Results,
+                _ => Focus::Entries,
+            },
+            max_width: matches.get_one::<u16>("max_width").copied(),
+            backup_max_entries: matches.get_one::<usize>("backup_max_entries").copied(),
+            backup_max_age: matches
+                .get_one::<u64>("backup_max_age_days")
+                .map(|days| Duration::from_secs(days * 24 * 60 * 60)),
+            test_filter: matches.get_one::<String>("test").cloned(),
+            time_format: matches.get_one::<String>("time_format").unwrap().clone(),
+            relative_time: matches.get_flag("relative_time"),
+            scroll_off: *matches.get_one::<usize>("scroll_off").unwrap(),
+            manual_apply: matches.get_flag("manual_apply"),
+            tabs: matches.get_one::<Vec<(String, String)>>("tabs").cloned(),
+            tab: matches
+                .get_one::<String>("tab")
+                .cloned()
+                .or(file_config.tab),
+            workdir: matches.get_one::<String>("workdir").map(PathBuf::from),
+            tool_paths: TOOL_PATH_FLAGS
+                .iter()
+                .filter_map(|(flag, tool)| {
+                    let path = matches.get_one::<String>(flag)?;
+                    Some((tool.to_string(), PathBuf::from(path)))
+                })
+                .collect(),
+            delay_model,
+            delay_models: matches
+                .get_one::<Vec<String>>("delay_models")
+                .cloned()
+                .unwrap_or_else(|| DELAY_MODELS.map(String::from).to_vec()),
+            pipeline_stages: matches.get_one::<usize>("pipeline_stages").copied(),
+            clock_period_ps: matches.get_one::<u64>("clock_period_ps").copied(),
+            no_state: matches.get_flag("no_state"),
+        }
+    }
+}
+
+/// Accepts `spec` only if chrono can format a time with it, so a bad format
+/// is a usage error rather than a panic while drawing.
+fn parse_time_format(spec: &str) -> Result<String, String> {
+    if StrftimeItems::new(spec).any(|item| item == Item::Error) {
+        Err(format!("invalid strftime format '{}'", spec))
+    } else {
+        Ok(spec.to_string())
+    }
+}
+
+/// Parses a `--delay-models` list such as `asap7,unit`.
+fn parse_delay_models(spec: &str) -> Result<Vec<String>, String> {
+    let models: Vec<String> = spec
+        .split(',')
+        .map(str::trim)
+        .filter(|model| !model.is_empty())
+        .map(String::from)
+        .collect();
+    if models.is_empty() {
+        return Err(String::from("no delay models listed"));
+    }
+    Ok(models)
+}
+
+/// Parses a `--tabs` list such as `delay,opt=optimized`, rejecting unknown
+/// or repeated stages.
+fn parse_tabs(spec: &str) -> Result<Vec<(String, String)>, String> {
+    let mut tabs: Vec<(String, String)> = Vec::new();
+    for item in spec.split(',').map(str::trim).filter(|t| !t.is_empty()) {
+        let (stage, label) = item.split_once('=').unwrap_or((item, ""));
+        let stage = stage.trim();
+        let Some((_, default_label)) = TABS.iter().find(|(name, _)| *name == stage) else {
+            let stages: Vec<&str> = TABS.iter().map(|(name, _)| *name).collect();
+            return Err(format!(
+                "unknown tab '{}'; valid tabs are: {}",
+                stage,
+                stages.join(", ")
+            ));
+        };
+        if tabs.iter().any(|(seen, _)| seen == stage) {
+            return Err(format!("tab '{}' listed twice", stage));
+        }
+        let label = match label.trim() {
+            "" => default_label.to_string(),
+            label => label.to_string(),
+        };
+        tabs.push((stage.to_string(), label));
+    }
+    if tabs.is_empty() {
+        return Err(String::from("no tabs listed"));
+    }
+    Ok(tabs)
+}
@@ -0,0 +1,70 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Defaults read from `dslx-watch-tui.toml`, found in the current directory
+//! or `$XDG_CONFIG_HOME`. Command-line flags take precedence over it.
+
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+/// Name of the configuration file.
+const CONFIG_FILE_NAME: &str = "dslx-watch-tui.toml";
+
+/// The settings a configuration file may give; `None` where it is silent.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct FileConfig {
+    pub(crate) dslx_stdlib_path: Option<String>,
+    pub(crate) delay_model: Option<String>,
+    pub(crate) debounce_ms: Option<u64>,
+    pub(crate) color_scheme: Option<String>,
+    // Stage name of the tab selected at startup.
+    pub(crate) tab: Option<String>,
+    // `[colors]` entries: color by theme element.
+    #[serde(default)]
+    pub(crate) colors: BTreeMap<String, String>,
+}
+
+/// The configuration file in effect: `./dslx-watch-tui.toml`, else the one
+/// in `$XDG_CONFIG_HOME` (`~/.config` when unset).
+fn config_file_path() -> Option<PathBuf> {
+    let local = PathBuf::from(CONFIG_FILE_NAME);
+    if local.is_file() {
+        return Some(local);
+    }
+    let config_home = match std::env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(config_home.join(CONFIG_FILE_NAME)).filter(|path| path.is_file())
+}
+
+/// Reads the configuration file, if there is one. A file that cannot be
+/// read or parsed is an error naming it, rather than silently ignored.
+pub(crate) fn load_config_file() -> Result<Option<FileConfig>, String> {
+    let Some(path) = config_file_path() else {
+        return Ok(None);
+    };
+    let text = fs::read_to_string(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    parse_config_file(&text)
+        .map(Some)
+        .map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Parses the configuration file's `text`; errors name the offending line
+/// and the key on it.
+fn parse_config_file(text: &str) -> Result<FileConfig, String> {
+    toml::from_str(text).map_err(|e| {
+        let message = e.message().trim_end();
+        let Some(span) = e.span() else {
+            return message.to_string();
+        };
+        let number = text[..span.start].matches('\n').count();
+        let line = text.lines().nth(number).unwrap_or("");
+        match line.split_once('=').map(|(key, _)| key.trim()) {
+            Some(key) if !key.is_empty() => format!("line {}: {}: {}", number + 1, key, message),
+            _ => format!("line {}: {}", number + 1, message),
+        }
+    })
+}
@@ -0,0 +1,79 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Parsing of `delay_info_main` output into a per-node table and a
+//! critical path summary.
+
+use regex::Regex;
+use serde::Serialize;
+
+/// One node on the critical path and its own delay.
+#[derive(Clone, Serialize)]
+pub(crate) struct DelayEntry {
+    pub(crate) node: String,
+    pub(crate) op: String,
+    pub(crate) delay_ps: u64,
+}
+
+/// Parses critical path lines such as
+/// `12ps (+ 12ps): add.3: bits[32] = add(a, b, id=3)`, taking the node's own
+/// (`+`) delay rather than the cumulative one.
+pub(crate) fn parse_delay_info(delay_info: &str) -> Vec<DelayEntry> {
+    let line_re =
+        Regex::new(r"^\s*\d+ps \(\+\s*(\d+)ps\): (?:ret )?([\w.]+): .+? = (\w+)\(").unwrap();
+    delay_info
+        .lines()
+        .filter_map(|line| line_re.captures(line))
+        .map(|cap| DelayEntry {
+            node: cap[2].to_string(),
+            op: cap[3].to_string(),
+            delay_ps: cap[1].parse().unwrap_or(0),
+        })
+        .collect()
+}
+
+/// Nodes listed in a [`DelaySummary`].
+const SUMMARY_TOP_NODES: usize = 3;
+
+/// The critical path's total delay and its slowest nodes, shown above the
+/// raw delay info.
+#[derive(Clone)]
+pub(crate) struct DelaySummary {
+    pub(crate) critical_path_ps: u64,
+    // Highest own delay first; nodes that add no delay are left out.
+    pub(crate) top_nodes: Vec<DelayEntry>,
+}
+
+/// Summarizes `delay_info`: the critical path delay is the largest
+/// cumulative delay on it. `None` when no critical path lines are found, so
+/// unexpected output is only shown raw.
+pub(crate) fn parse_delay_summary(delay_info: &str) -> Option<DelaySummary> {
+    let total_re = Regex::new(r"^\s*(\d+)ps \(\+").unwrap();
+    let critical_path_ps = delay_info
+        .lines()
+        .filter_map(|line| total_re.captures(line)?[1].parse::<u64>().ok())
+        .max()?;
+    let mut top_nodes = parse_delay_info(delay_info);
+    top_nodes.retain(|entry| entry.delay_ps > 0);
+    top_nodes.sort_by_key(|entry| std::cmp::Reverse(entry.delay_ps));
+    top_nodes.truncate(SUMMARY_TOP_NODES);
+    Some(DelaySummary {
+        critical_path_ps,
+        top_nodes,
+    })
+}
+
+/// The delay table as CSV with a `node,op,delay_ps` header. Node names and
+/// ops are IR identifiers, so no field needs quoting.
+pub(crate) fn delay_csv(entries: &[DelayEntry]) -> String {
+    let mut csv = String::from("node,op,delay_ps\n");
+    for entry in entries {
+        csv.push_str(&format!("{},{},{}\n", entry.node, entry.op, entry.delay_ps));
+    }
+    csv
+}
+
+/// The delay table as a JSON array of `{node, op, delay_ps}` objects.
+pub(crate) fn delay_json(entries: &[DelayEntry]) -> String {
+    let json = serde_json::to_string_pretty(entries).expect("DelayEntry is always serializable");
+    json + "\n"
+}
@@ -0,0 +1,165 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Line-level diffs between two versions of an artifact.
+
+use crate::theme::Theme;
+use ratatui::style::Style;
+use ratatui::text::{Span, Spans};
+
+/// Most cells of the longest common subsequence table a diff fills in, about
+/// 128 MB, so huge IR does not exhaust memory.
+const DIFF_CELL_LIMIT: usize = 16_000_000;
+
+/// One line of a line-level diff between two texts.
+#[derive(Clone)]
+pub(crate) enum DiffLine {
+    Same(String),
+    Added(String),
+    Removed(String),
+    Changed(String),
+}
+
+impl DiffLine {
+    pub(crate) fn to_spans(&self, theme: &Theme) -> Spans<'static> {
+        let style = match self {
+            DiffLine::Same(_) => Style::default(),
+            DiffLine::Added(_) => theme.diff_added,
+            DiffLine::Removed(_) => theme.diff_removed,
+            DiffLine::Changed(_) => theme.diff_changed,
+        };
+        Spans::from(Span::styled(self.to_text(), style))
+    }
+
+    /// The line with its `+`/`−`/`~` marker, as shown.
+    pub(crate) fn to_text(&self) -> String {
+        let (prefix, text) = match self {
+            DiffLine::Same(t) => (" ", t),
+            DiffLine::Added(t) => ("+", t),
+            DiffLine::Removed(t) => ("−", t),
+            DiffLine::Changed(t) => ("~", t),
+        };
+        format!("{} {}", prefix, text)
+    }
+}
+
+/// A line diff computed once per build rather than on every frame.
+#[derive(Clone)]
+pub(crate) enum LineDiff {
+    Lines(Vec<DiffLine>),
+    // Too many lines differ to diff them within the memory limit.
+    TooLarge,
+}
+
+impl Default for LineDiff {
+    fn default() -> Self {
+        LineDiff::Lines(Vec::new())
+    }
+}
+
+impl LineDiff {
+    pub(crate) fn to_spans(&self, theme: &Theme) -> Vec<Spans<'static>> {
+        match self {
+            LineDiff::Lines(lines) => lines.iter().map(|line| line.to_spans(theme)).collect(),
+            LineDiff::TooLarge => vec![Spans::from(Span::styled(
+                "[ diff too large to show ]",
+                theme.placeholder,
+            ))],
+        }
+    }
+}
+
+/// Computes a line diff of `old` -> `new` via longest common subsequence. A
+/// run of removals immediately followed by additions is reported as changed
+/// lines (showing the new text) paired up one-for-one.
+pub(crate) fn diff_lines(old: &str, new: &str) -> LineDiff {
+    diff(old, new, true).map_or(LineDiff::TooLarge, LineDiff::Lines)
+}
+
+/// Like [`diff_lines`], but keeps every removed and added line, as `diff -u`
+/// does, for texts that differ too much for changed lines to be useful.
+/// `None` when too many lines differ to diff them within the memory limit.
+pub(crate) fn unified_diff_lines(old: &str, new: &str) -> Option<Vec<DiffLine>> {
+    diff(old, new, false)
+}
+
+fn diff(old: &str, new: &str, pair_changes: bool) -> Option<Vec<DiffLine>> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    // Only the lines between the common prefix and suffix need the table.
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let a = &old[prefix..old.len() - suffix];
+    let b = &new[prefix..new.len() - suffix];
+    if (a.len() + 1).saturating_mul(b.len() + 1) > DIFF_CELL_LIMIT {
+        return None;
+    }
+    // lcs[i][j] is the LCS length of a[i..] and b[j..].
+    let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut out: Vec<DiffLine> = old[..prefix]
+        .iter()
+        .map(|t| DiffLine::Same(t.to_string()))
+        .collect();
+    let (mut i, mut j) = (0, 0);
+    let mut removed: Vec<&str> = Vec::new();
+    let mut added: Vec<&str> = Vec::new();
+    let flush = |out: &mut Vec<DiffLine>, removed: &mut Vec<&str>, added: &mut Vec<&str>| {
+        let paired = if pair_changes {
+            removed.len().min(added.len())
+        } else {
+            0
+        };
+        out.extend(
+            added[..paired]
+                .iter()
+                .map(|t| DiffLine::Changed(t.to_string())),
+        );
+        out.extend(
+            removed[paired..]
+                .iter()
+                .map(|t| DiffLine::Removed(t.to_string())),
+        );
+        out.extend(
+            added[paired..]
+                .iter()
+                .map(|t| DiffLine::Added(t.to_string())),
+        );
+        removed.clear();
+        added.clear();
+    };
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            flush(&mut out, &mut removed, &mut added);
+            out.push(DiffLine::Same(a[i].to_string()));
+            i += 1;
+            j += 1;
+        } else if j < b.len() && (i == a.len() || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            added.push(b[j]);
+            j += 1;
+        } else {
+            removed.push(a[i]);
+            i += 1;
+        }
+    }
+    flush(&mut out, &mut removed, &mut added);
+    out.extend(
+        old[old.len() - suffix..]
+            .iter()
+            .map(|t| DiffLine::Same(t.to_string())),
+    );
+    Some(out)
+}
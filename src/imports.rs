@@ -0,0 +1,39 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Resolution of the DSLX modules a file imports, so that editing one of
+//! them rebuilds the file.
+
+use regex::Regex;
+use std::fs;
+use std::path::PathBuf;
+
+/// The files `source` imports, directly or through the modules it imports.
+/// `import a.b;` is looked up as `a/b.x` under the first of `search_dirs`
+/// that has it; imports found in none of them are skipped.
+pub(crate) fn imported_files(source: &str, search_dirs: &[PathBuf]) -> Vec<PathBuf> {
+    let import_re = Regex::new(r"(?m)^\s*import\s+([\w.]+)").unwrap();
+    let mut found: Vec<PathBuf> = Vec::new();
+    let mut pending = vec![source.to_string()];
+    while let Some(text) = pending.pop() {
+        for cap in import_re.captures_iter(&text) {
+            let relative = format!("{}.x", cap[1].replace('.', "/"));
+            let Some(path) = search_dirs
+                .iter()
+                .map(|dir| dir.join(&relative))
+                .find(|path| path.is_file())
+            else {
+                continue;
+            };
+            // Event paths are canonical, and so is the watch set.
+            let path = fs::canonicalize(&path).unwrap_or(path);
+            if found.contains(&path) {
+                continue;
+            }
+            if let Ok(imported) = fs::read_to_string(&path) {
+                pending.push(imported);
+            }
+            found.push(path);
+        }
+    }
+    found
+}
@@ -0,0 +1,627 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Lightweight text analyses of XLS IR packages.
+
+use regex::Regex;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+/// The DSLX name of a mangled IR function name, e.g. `main` for
+/// `__my_file__main`; names that are not mangled are returned unchanged.
+pub(crate) fn display_name(ir_name: &str) -> &str {
+    ir_name
+        .strip_prefix("__")
+        .and_then(|rest| rest.split_once("__"))
+        .map_or(ir_name, |(_, name)| name)
+}
+
+/// Extracts "(params) -> ret" for every IR function, keyed by name, with the
+/// `id=N` annotations on parameters dropped.
+pub(crate) fn ir_signatures(ir: &str) -> HashMap<String, String> {
+    let sig_re = Regex::new(r"fn (\w+)\((.*?)\) -> (.+?) \{").unwrap();
+    let id_re = Regex::new(r" id=\d+").unwrap();
+    sig_re
+        .captures_iter(ir)
+        .map(|cap| {
+            let params = id_re.replace_all(&cap[2], "");
+            (cap[1].to_string(), format!("({}) -> {}", params, &cap[3]))
+        })
+        .collect()
+}
+
+/// Maps each IR function to the functions it calls through `invoke`, `map`,
+/// `counted_for` and friends, in definition order.
+fn ir_call_graph(ir: &str) -> Vec<(String, Vec<String>)> {
+    let fn_re = Regex::new(r"^(?:top )?fn (\w+)").unwrap();
+    let callee_re = Regex::new(r"\b(?:to_apply|body)=(\w+)").unwrap();
+    let mut graph: Vec<(String, Vec<String>)> = Vec::new();
+    for line in ir.lines() {
+        if let Some(cap) = fn_re.captures(line) {
+            graph.push((cap[1].to_string(), Vec::new()));
+        } else if let Some((_, callees)) = graph.last_mut() {
+            for cap in callee_re.captures_iter(line) {
+                if !callees.iter().any(|c| c == &cap[1]) {
+                    callees.push(cap[1].to_string());
+                }
+            }
+        }
+    }
+    graph
+}
+
+/// Renders `ir_call_graph` as a flat adjacency listing. Built from the unopt
+/// IR, since opt IR has usually had every callee inlined.
+pub(crate) fn call_graph_summary(ir: &str) -> String {
+    ir_call_graph(ir)
+        .iter()
+        .map(|(caller, callees)| {
+            let callees = if callees.is_empty() {
+                "—".to_string()
+            } else {
+                callees.join(", ")
+            };
+            format!("{} calls: {}", caller, callees)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Counts the nodes defined in each `fn` of an IR package, keyed by function
+/// name. Every node assignment in the IR text is a line containing ` = `.
+pub(crate) fn ir_op_counts(ir: &str) -> HashMap<String, usize> {
+    let fn_re = Regex::new(r"^(?:top )?fn (\w+)").unwrap();
+    let mut counts = HashMap::new();
+    let mut current: Option<String> = None;
+    for line in ir.lines() {
+        if let Some(cap) = fn_re.captures(line) {
+            current = Some(cap[1].to_string());
+            counts.insert(cap[1].to_string(), 0);
+        } else if line.starts_with('}') {
+            current = None;
+        } else if let Some(name) = &current {
+            if line.contains(" = ") {
+                *counts.get_mut(name).unwrap() += 1;
+            }
+        }
+    }
+    counts
+}
+
+/// Node count and op tally of one function, for the opt IR tab's header.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct OpStats {
+    /// Nodes other than parameters.
+    pub(crate) nodes: usize,
+    /// Nodes per op, most frequent first and then by name.
+    pub(crate) ops: Vec<(String, usize)>,
+}
+
+/// Tallies the ops of `function` in `ir`, parameters excluded; `None` if
+/// `ir` has no such function.
+pub(crate) fn op_stats(ir: &str, function: &str) -> Option<OpStats> {
+    let (_, nodes) = ir_nodes(ir)
+        .into_iter()
+        .find(|(name, _)| name == function)?;
+    let mut tally: BTreeMap<String, usize> = BTreeMap::new();
+    for node in nodes.into_iter().filter(|n| n.op != "param") {
+        *tally.entry(node.op).or_insert(0) += 1;
+    }
+    let mut ops: Vec<(String, usize)> = tally.into_iter().collect();
+    ops.sort_by_key(|(_, count)| Reverse(*count));
+    Some(OpStats {
+        nodes: ops.iter().map(|(_, count)| count).sum(),
+        ops,
+    })
+}
+
+/// A node definition parsed from a line of IR text.
+pub(crate) struct IrNode {
+    pub(crate) name: String,
+    pub(crate) op: String,
+    /// Names of the operand nodes, in order, including those passed by
+    /// keyword such as `cases=[a, b]`; attributes such as `id=` excluded.
+    pub(crate) operands: Vec<String>,
+}
+
+/// Fan-in and fan-out of one node within its function.
+pub(crate) struct NodeFan {
+    pub(crate) function: String,
+    pub(crate) node: IrNode,
+    pub(crate) users: Vec<String>,
+}
+
+/// Splits `args` on commas that are not nested inside brackets or parens.
+fn split_top_level(args: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0i32;
+    let mut start = 0;
+    for (i, c) in args.char_indices() {
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(args[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(args[start..].trim());
+    parts.into_iter().filter(|p| !p.is_empty()).collect()
+}
+
+/// Whether the keyword argument `key` of an `op` node names operands rather
+/// than an attribute such as `id=` or `to_apply=`.
+fn is_operand_keyword(op: &str, key: &str) -> bool {
+    match key {
+        "cases" | "default" | "indices" | "invariant_args" | "predicate" => true,
+        // Elsewhere `value=` is a literal's value.
+        "param" | "value" => op == "next_value",
+        _ => false,
+    }
+}
+
+/// The operand names in one argument of an `op` node: the argument itself,
+/// or those in a keyword operand list such as `cases=[a, b]`.
+fn arg_operands<'a>(op: &str, arg: &'a str) -> Vec<&'a str> {
+    let Some((key, value)) = arg.split_once('=') else {
+        return vec![arg];
+    };
+    if !is_operand_keyword(op, key) {
+        return Vec::new();
+    }
+    match value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+        Some(list) => split_top_level(list),
+        None => vec![value],
+    }
+}
+
+/// Parses the nodes of every function, keyed by function name in definition
+/// order. Parameters appear as `param` nodes with no operands.
+pub(crate) fn ir_nodes(ir: &str) -> Vec<(String, Vec<IrNode>)> {
+    let fn_re = Regex::new(r"^(?:top )?fn (\w+)\((.*?)\) ->").unwrap();
+    let param_re = Regex::new(r"(\w+): [^,]*?id=\d+").unwrap();
+    let node_re = Regex::new(r"^\s*(?:ret )?([\w.]+): .+? = (\w+)\((.*)\)\s*$").unwrap();
+    let mut functions: Vec<(String, Vec<IrNode>)> = Vec::new();
+    for line in ir.lines() {
+        if let Some(cap) = fn_re.captures(line) {
+            let params = param_re
+                .captures_iter(&cap[2])
+                .map(|p| IrNode {
+                    name: p[1].to_string(),
+                    op: "param".to_string(),
+                    operands: Vec::new(),
+                })
+                .collect();
+            functions.push((cap[1].to_string(), params));
+        } else if let (Some(cap), Some((_, nodes))) = (node_re.captures(line), functions.last_mut())
+        {
+            let operands = split_top_level(&cap[3])
+                .into_iter()
+                .flat_map(|arg| arg_operands(&cap[2], arg))
+                .map(str::to_string)
+                .collect();
+            nodes.push(IrNode {
+                name: cap[1].to_string(),
+                op: cap[2].to_string(),
+                operands,
+            });
+        }
+    }
+    functions
+}
+
+/// Bit width of every node and parameter in `ir` whose type
+/// [`type_bits`] recognizes, keyed by name.
+pub(crate) fn node_bits(ir: &str) -> HashMap<String, u64> {
+    let fn_re = Regex::new(r"^(?:top )?fn \w+\((.*?)\) ->").unwrap();
+    let node_re = Regex::new(r"^\s*(?:ret )?([\w.]+): (.+?) = \w+\(").unwrap();
+    let mut bits = HashMap::new();
+    for line in ir.lines() {
+        if let Some(cap) = fn_re.captures(line) {
+            for param in split_top_level(&cap[1]) {
+                let Some((name, ty)) = param.split_once(": ") else {
+                    continue;
+                };
+                let ty = ty.rsplit_once(" id=").map_or(ty, |(ty, _)| ty);
+                if let Some(width) = type_bits(ty) {
+                    bits.insert(name.to_string(), width);
+                }
+            }
+        } else if let Some(cap) = node_re.captures(line) {
+            if let Some(width) = type_bits(&cap[2]) {
+                bits.insert(cap[1].to_string(), width);
+            }
+        }
+    }
+    bits
+}
+
+/// Graphviz DOT of the node graph of `function` in `ir`: one box per node
+/// labelled with its name and op, and an edge from each operand labelled with
+/// its position. `None` if `ir` does not define `function`.
+pub(crate) fn ir_dot(ir: &str, function: &str) -> Option<String> {
+    let (_, nodes) = ir_nodes(ir)
+        .into_iter()
+        .find(|(name, _)| name == function)?;
+    let names: HashSet<&str> = nodes.iter().map(|n| n.name.as_str()).collect();
+    let mut dot = format!("digraph \"{}\" {{\n  node [shape=box];\n", function);
+    for node in &nodes {
+        dot.push_str(&format!(
+            "  \"{}\" [label=\"{}\\n{}\"];\n",
+            node.name, node.name, node.op
+        ));
+    }
+    for node in &nodes {
+        for (i, operand) in node.operands.iter().enumerate() {
+            if names.contains(operand.as_str()) {
+                dot.push_str(&format!(
+                    "  \"{}\" -> \"{}\" [label=\"{}\"];\n",
+                    operand, node.name, i
+                ));
+            }
+        }
+    }
+    dot.push_str("}\n");
+    Some(dot)
+}
+
+/// Finds `name` in the first function defining it and collects the nodes
+/// that use it.
+pub(crate) fn node_fan(ir: &str, name: &str) -> Option<NodeFan> {
+    for (function, nodes) in ir_nodes(ir) {
+        let users: Vec<String> = nodes
+            .iter()
+            .filter(|n| n.operands.iter().any(|o| o == name))
+            .map(|n| n.name.clone())
+            .collect();
+        if let Some(node) = nodes.into_iter().find(|n| n.name == name) {
+            return Some(NodeFan {
+                function,
+                node,
+                users,
+            });
+        }
+    }
+    None
+}
+
+/// Number of operand uses of every node in `ir`, within its own function, as
+/// (node name, op, fan-out), highest fan-out first.
+pub(crate) fn compute_fanout(ir: &str) -> Vec<(String, String, usize)> {
+    let mut fanout = Vec::new();
+    for (_, nodes) in ir_nodes(ir) {
+        let mut uses: HashMap<&str, usize> = HashMap::new();
+        for operand in nodes.iter().flat_map(|n| &n.operands) {
+            *uses.entry(operand.as_str()).or_insert(0) += 1;
+        }
+        for node in &nodes {
+            let count = uses.get(node.name.as_str()).copied().unwrap_or(0);
+            fanout.push((node.name.clone(), node.op.clone(), count));
+        }
+    }
+    fanout.sort_by(|a, b| b.2.cmp(&a.2).then_with(|| a.0.cmp(&b.0)));
+    fanout
+}
+
+/// The ten highest fan-out values of the opt IR as an aligned table,
+/// followed by the state carried by each proc of the unopt IR, if any.
+pub(crate) fn stats_summary(opt_ir: &str, unopt_ir: &str) -> String {
+    let mut lines = vec![format!("{:<24} {:<16} {:>7}", "value", "op", "fan-out")];
+    lines.extend(
+        compute_fanout(opt_ir)
+            .into_iter()
+            .take(10)
+            .map(|(name, op, count)| format!("{:<24} {:<16} {:>7}", name, op, count)),
+    );
+    let procs = proc_state(unopt_ir);
+    if !procs.is_empty() {
+        lines.push(String::new());
+        lines.push(format!(
+            "{:<24} {:>8} {:>6}  widths",
+            "proc", "elements", "bits"
+        ));
+        for (name, state) in procs {
+            let bits = state
+                .iter()
+                .map(|ty| type_bits(ty))
+                .sum::<Option<u64>>()
+                .map_or_else(|| String::from("?"), |bits| bits.to_string());
+            lines.push(format!(
+                "{:<24} {:>8} {:>6}  {}",
+                name,
+                state.len(),
+                bits,
+                state.join(", ")
+            ));
+        }
+    }
+    lines.join("\n")
+}
+
+/// The state element types of every proc in `ir`, keyed by proc name in
+/// definition order. Token-typed state is left out.
+fn proc_state(ir: &str) -> Vec<(String, Vec<String>)> {
+    let proc_re = Regex::new(r"^(?:top )?proc (\w+)(?:<.*>)?\((.*)\)\s*\{").unwrap();
+    let id_re = Regex::new(r"\s+id=\d+$").unwrap();
+    ir.lines()
+        .filter_map(|line| proc_re.captures(line))
+        .map(|cap| {
+            let params = cap[2].split("init={").next().unwrap_or("");
+            let state = split_top_level(params)
+                .into_iter()
+                .filter_map(|param| param.split_once(": "))
+                .map(|(_, ty)| id_re.replace(ty.trim(), "").into_owned())
+                .filter(|ty| ty != "token")
+                .collect();
+            (cap[1].to_string(), state)
+        })
+        .collect()
+}
+
+/// Total bit width of an IR type such as `bits[8]`, `(bits[1], bits[8])` or
+/// `bits[8][4]`; `None` for types it does not recognize.
+fn type_bits(ty: &str) -> Option<u64> {
+    let ty = ty.trim();
+    if ty == "token" {
+        return Some(0);
+    }
+    if let Some(inner) = ty.strip_prefix('(').and_then(|t| t.strip_suffix(')')) {
+        return split_top_level(inner).into_iter().map(type_bits).sum();
+    }
+    let (element, count) = ty.strip_suffix(']')?.rsplit_once('[')?;
+    let count: u64 = count.parse().ok()?;
+    if element == "bits" {
+        Some(count)
+    } else {
+        Some(type_bits(element)? * count)
+    }
+}
+
+/// Number of nodes of each op across every function of `ir`, parameters
+/// excluded.
+fn op_histogram(ir: &str) -> BTreeMap<String, usize> {
+    let mut histogram = BTreeMap::new();
+    for (_, nodes) in ir_nodes(ir) {
+        for node in nodes.iter().filter(|n| n.op != "param") {
+            *histogram.entry(node.op.clone()).or_insert(0) += 1;
+        }
+    }
+    histogram
+}
+
+/// Width of a `bits[N]` type; `None` for tuples, arrays and tokens.
+fn bits_width(ty: &str) -> Option<&str> {
+    ty.strip_prefix("bits[")?.strip_suffix(']')
+}
+
+/// Summarizes what changed structurally between two builds of the IR, e.g.
+/// "added 2 add, removed 1 sel, return width 32→33". Empty when the op
+/// counts and `entry`'s return type are unchanged.
+pub(crate) fn ir_changelog(old: &str, new: &str, entry: &str) -> String {
+    let old_ops = op_histogram(old);
+    let new_ops = op_histogram(new);
+    let mut added = Vec::new();
+    let mut removed = Vec::new();
+    for op in old_ops
+        .keys()
+        .chain(new_ops.keys())
+        .collect::<BTreeSet<_>>()
+    {
+        let before = old_ops.get(op).copied().unwrap_or(0);
+        let after = new_ops.get(op).copied().unwrap_or(0);
+        if after > before {
+            added.push(format!("{} {}", after - before, op));
+        } else if before > after {
+            removed.push(format!("{} {}", before - after, op));
+        }
+    }
+    let mut changes = Vec::new();
+    if !added.is_empty() {
+        changes.push(format!("added {}", added.join(", ")));
+    }
+    if !removed.is_empty() {
+        changes.push(format!("removed {}", removed.join(", ")));
+    }
+    let return_type = |ir: &str| {
+        ir_signatures(ir)
+            .get(entry)
+            .and_then(|sig| sig.rsplit_once(" -> ").map(|(_, ret)| ret.to_string()))
+    };
+    if let (Some(old_ret), Some(new_ret)) = (return_type(old), return_type(new)) {
+        if old_ret != new_ret {
+            changes.push(match (bits_width(&old_ret), bits_width(&new_ret)) {
+                (Some(before), Some(after)) => format!("return width {}→{}", before, after),
+                _ => format!("return type {}→{}", old_ret, new_ret),
+            });
+        }
+    }
+    changes.join(", ")
+}
+
+/// Replaces the body of every function named in `folded` with a one-line
+/// summary, leaving the `fn` header in place so it can be unfolded again.
+pub(crate) fn fold_functions(ir: &str, folded: &HashSet<String>) -> String {
+    let fn_re = Regex::new(r"^(?:top )?fn (\w+)").unwrap();
+    let mut out = Vec::new();
+    let mut lines = ir.lines();
+    while let Some(line) = lines.next() {
+        match fn_re.captures(line) {
+            Some(cap) if folded.contains(&cap[1]) => {
+                let hidden = lines.by_ref().take_while(|l| !l.starts_with('}')).count();
+                out.push(format!("{} … }}  [{} lines folded]", line, hidden));
+            }
+            _ => out.push(line.to_string()),
+        }
+    }
+    out.join("\n")
+}
+
+/// Name of the function whose header or body is at `line` of `ir`.
+pub(crate) fn function_at_line(ir: &str, line: usize) -> Option<String> {
+    let fn_re = Regex::new(r"^(?:top )?fn (\w+)").unwrap();
+    let lines: Vec<&str> = ir.lines().take(line + 1).collect();
+    for (i, text) in lines.iter().enumerate().rev() {
+        if let Some(cap) = fn_re.captures(text) {
+            return Some(cap[1].to_string());
+        }
+        if text.starts_with('}') && i != line {
+            return None;
+        }
+    }
+    None
+}
+
+/// Lists the `assert` and `cover` nodes of `opt_ir` by function. With the
+/// interpreter's output and whether the tests passed, an assert whose label or
+/// message the output mentions is marked failed, and the rest held when the
+/// tests passed. The interpreter does not report cover hits.
+pub(crate) fn verification_summary(opt_ir: &str, test_log: Option<(&str, bool)>) -> String {
+    let fn_re = Regex::new(r"^(?:top )?fn (\w+)").unwrap();
+    let node_re = Regex::new(r"^\s*(?:ret )?([\w.]+): token = (assert|cover)\((.*)\)\s*$").unwrap();
+    let kwarg_re = Regex::new(r#"\b(label|message)="((?:[^"\\]|\\.)*)""#).unwrap();
+    let mut lines = vec![format!(
+        "{:<24} {:<7} {:<16} {:<8} label / message",
+        "function", "kind", "node", "tests"
+    )];
+    let mut function = "";
+    for line in opt_ir.lines() {
+        if let Some(cap) = fn_re.captures(line) {
+            function = cap.get(1).unwrap().as_str();
+            continue;
+        }
+        let Some(cap) = node_re.captures(line) else {
+            continue;
+        };
+        let mut label = "";
+        let mut message = "";
+        for kwarg in kwarg_re.captures_iter(&cap[3]) {
+            match &kwarg[1] {
+                "label" => label = kwarg.get(2).unwrap().as_str(),
+                _ => message = kwarg.get(2).unwrap().as_str(),
+            }
+        }
+        let status = match (&cap[2], test_log) {
+            ("assert", Some((log, _)))
+                if [label, message]
+                    .iter()
+                    .any(|text| !text.is_empty() && log.contains(text)) =>
+            {
+                "failed"
+            }
+            ("assert", Some((_, true))) => "held",
+            _ => "-",
+        };
+        let text = match (label, message) {
+            ("", message) => message.to_string(),
+            (label, "") => label.to_string(),
+            (label, message) => format!("{}: {}", label, message),
+        };
+        lines.push(format!(
+            "{:<24} {:<7} {:<16} {:<8} {}",
+            function, &cap[2], &cap[1], status, text
+        ));
+    }
+    if lines.len() == 1 {
+        lines.push(String::from("[ no assert!/cover! in the opt IR ]"));
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A zero-width parameter and literal alongside an 8-bit one.
+    const ZERO_WIDTH_IR: &str = "package p
+
+fn f(a: bits[0] id=1, b: bits[8] id=2) -> bits[8] {
+  literal.3: bits[0] = literal(value=0, id=3)
+  tuple.4: (bits[0], bits[8]) = tuple(a, b, id=4)
+  ret tuple_index.5: bits[8] = tuple_index(tuple.4, index=1, id=5)
+}
+";
+
+    #[test]
+    fn ir_nodes_includes_zero_width_params_and_nodes() {
+        let functions = ir_nodes(ZERO_WIDTH_IR);
+        assert_eq!(functions.len(), 1);
+        let (name, nodes) = &functions[0];
+        assert_eq!(name, "f");
+        let summary: Vec<(&str, &str, Vec<&str>)> = nodes
+            .iter()
+            .map(|n| {
+                let operands = n.operands.iter().map(String::as_str).collect();
+                (n.name.as_str(), n.op.as_str(), operands)
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("a", "param", vec![]),
+                ("b", "param", vec![]),
+                ("literal.3", "literal", vec![]),
+                ("tuple.4", "tuple", vec!["a", "b"]),
+                ("tuple_index.5", "tuple_index", vec!["tuple.4"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn compute_fanout_counts_uses_of_zero_width_values() {
+        let fanout = compute_fanout(ZERO_WIDTH_IR);
+        let expected = [
+            ("a", "param", 1),
+            ("b", "param", 1),
+            ("tuple.4", "tuple", 1),
+            ("literal.3", "literal", 0),
+            ("tuple_index.5", "tuple_index", 0),
+        ];
+        assert_eq!(fanout.len(), expected.len());
+        for ((name, op, count), (want_name, want_op, want_count)) in fanout.iter().zip(expected) {
+            assert_eq!(
+                (name.as_str(), op.as_str(), *count),
+                (want_name, want_op, want_count)
+            );
+        }
+    }
+
+    #[test]
+    fn node_bits_records_zero_widths() {
+        let bits = node_bits(ZERO_WIDTH_IR);
+        assert_eq!(bits.get("a"), Some(&0));
+        assert_eq!(bits.get("b"), Some(&8));
+        assert_eq!(bits.get("literal.3"), Some(&0));
+        assert_eq!(bits.get("tuple.4"), Some(&8));
+        assert_eq!(bits.get("tuple_index.5"), Some(&8));
+    }
+
+    #[test]
+    fn type_bits_handles_zero_width_types() {
+        assert_eq!(type_bits("bits[0]"), Some(0));
+        assert_eq!(type_bits("(bits[0], bits[8])"), Some(8));
+        assert_eq!(type_bits("bits[0][4]"), Some(0));
+        assert_eq!(type_bits("(bits[0])[3]"), Some(0));
+        assert_eq!(type_bits("bits[x]"), None);
+    }
+
+    #[test]
+    fn stats_summary_reports_zero_width_state() {
+        let unopt_ir = "package p
+
+proc counter(tok: token, s: bits[0], n: bits[4], init={token, 0, 0}) {
+}
+";
+        let summary = stats_summary(ZERO_WIDTH_IR, unopt_ir);
+        let lines: Vec<&str> = summary.lines().collect();
+        assert_eq!(lines[1], format!("{:<24} {:<16} {:>7}", "a", "param", 1));
+        assert_eq!(lines[6], "");
+        assert_eq!(
+            lines[8],
+            format!(
+                "{:<24} {:>8} {:>6}  {}",
+                "counter", 2, 4, "bits[0], bits[4]"
+            )
+        );
+    }
+}
@@ -0,0 +1,36 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! A TUI that watches a DSLX file and shows the artifacts the XLS toolchain
+//! generates from it: unoptimized and optimized IR, delay info and test
+//! results, rebuilt on every save.
+//!
+//! The binary is a thin wrapper around [`run_tui`]. Embedders can drive the
+//! same session with their own [`Config`] and, via [`run_tui_with_runner`],
+//! their own [`ToolRunner`] in place of the real xlsynth binaries.
+
+mod app;
+mod batch;
+mod clipboard;
+mod config;
+mod config_file;
+mod delay;
+mod diff;
+mod imports;
+mod ir;
+mod reduce;
+mod report;
+mod schedule;
+mod search;
+mod state;
+mod theme;
+mod tools;
+mod tui;
+mod ui;
+mod watch;
+
+pub use app::{App, Focus};
+pub use batch::{run_batch, run_batch_with_runner, BuildResult, EntryResult};
+pub use config::Config;
+pub use theme::Theme;
+pub use tools::{ToolRunner, XlsynthToolRunner, REQUIRED_TOOLS};
+pub use tui::{run_tui, run_tui_with_runner};
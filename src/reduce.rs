@@ -0,0 +1,122 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Naive line-based delta reduction of a DSLX file that makes a tool fail,
+//! for producing minimal reproducers.
+
+use crate::tools::ToolRunner;
+use std::fs;
+use std::path::Path;
+use std::process::Output;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// How a candidate input failed: the stage and the first line of its
+/// stderr. A candidate only reproduces the original failure when both match.
+#[derive(Clone, PartialEq)]
+pub(crate) struct Failure {
+    pub(crate) stage: String,
+    first_line: String,
+}
+
+impl Failure {
+    fn new(stage: &str, stderr: &[u8]) -> Self {
+        Failure {
+            stage: stage.to_string(),
+            first_line: String::from_utf8_lossy(stderr)
+                .lines()
+                .next()
+                .unwrap_or("")
+                .to_string(),
+        }
+    }
+}
+
+/// Runs the stages needed to reach `stage` on the DSLX file at `path`,
+/// returning the first failure. IR files are written next to `path`.
+pub(crate) fn run_until(
+    runner: &dyn ToolRunner,
+    path: &Path,
+    stdlib: Option<&str>,
+    top: &str,
+    delay_model: &str,
+    stage: &str,
+) -> Option<Failure> {
+    let path = path.to_string_lossy().into_owned();
+    let mut dslx_args = vec![path.clone()];
+    if let Some(stdlib) = stdlib {
+        dslx_args.extend(["--dslx_stdlib_path".to_string(), stdlib.to_string()]);
+    }
+    let failed = |tool: &str, output: &Output| {
+        (!output.status.success()).then(|| Failure::new(tool, &output.stderr))
+    };
+    if stage == "dslx_interpreter_main" {
+        let output = runner.run(stage, &dslx_args).ok()?;
+        return failed(stage, &output);
+    }
+
+    let output = runner.run("ir_converter_main", &dslx_args).ok()?;
+    if !output.status.success() || stage == "ir_converter_main" {
+        return failed("ir_converter_main", &output);
+    }
+    let unopt_file = format!("{}.unopt.ir", path);
+    fs::write(&unopt_file, &output.stdout).ok()?;
+    let output = runner
+        .run(
+            "opt_main",
+            &[unopt_file, "--top".to_string(), top.to_string()],
+        )
+        .ok()?;
+    if !output.status.success() || stage == "opt_main" {
+        return failed("opt_main", &output);
+    }
+    let opt_file = format!("{}.opt.ir", path);
+    fs::write(&opt_file, &output.stdout).ok()?;
+    let output = runner
+        .run(
+            "delay_info_main",
+            &[
+                opt_file,
+                "--delay_model".to_string(),
+                delay_model.to_string(),
+            ],
+        )
+        .ok()?;
+    failed("delay_info_main", &output)
+}
+
+/// Repeatedly drops chunks of lines from `source`, halving the chunk size
+/// whenever no chunk can go, keeping each removal for which `reproduces`
+/// still holds. Stops at single lines, once `time_limit` has passed or once
+/// `cancel` is set, and returns the smallest reproducing source found.
+pub(crate) fn reduce_lines(
+    source: &str,
+    time_limit: Duration,
+    cancel: &AtomicBool,
+    mut reproduces: impl FnMut(&str) -> bool,
+) -> String {
+    let start = Instant::now();
+    let running = || start.elapsed() < time_limit && !cancel.load(Ordering::Relaxed);
+    let mut lines: Vec<&str> = source.lines().collect();
+    let mut chunk = (lines.len() / 2).max(1);
+    while running() {
+        let mut removed_any = false;
+        let mut i = 0;
+        while i < lines.len() && running() {
+            let end = (i + chunk).min(lines.len());
+            let candidate: Vec<&str> = lines[..i].iter().chain(&lines[end..]).copied().collect();
+            if reproduces(&candidate.join("\n")) {
+                lines = candidate;
+                removed_any = true;
+            } else {
+                i += chunk;
+            }
+        }
+        if chunk == 1 && !removed_any {
+            break;
+        }
+        if !removed_any {
+            chunk /= 2;
+        }
+    }
+    lines.join("\n") + "\n"
+}
@@ -0,0 +1,162 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Self-contained HTML build report, written by the `:report` command.
+
+use crate::app::App;
+use crate::ir::ir_op_counts;
+use regex::Regex;
+
+const STYLE: &str = "
+body { background: #1e1e1e; color: #d4d4d4; font-family: sans-serif; margin: 2em; }
+h1 { font-size: 1.4em; }
+.meta { color: #888; }
+pre { background: #252526; padding: 1em; overflow-x: auto; }
+summary { cursor: pointer; font-weight: bold; margin: 0.5em 0; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid #444; padding: 0.2em 0.8em; text-align: left; }
+.ok { color: #6a9955; }
+.err { color: #f44747; }
+.kw { color: #569cd6; }
+.num { color: #b5cea8; }
+.cmt { color: #6a9955; }
+";
+
+const DSLX_KEYWORDS: &[&str] = &[
+    "fn",
+    "pub",
+    "let",
+    "const",
+    "struct",
+    "enum",
+    "type",
+    "import",
+    "use",
+    "match",
+    "if",
+    "else",
+    "for",
+    "in",
+    "proc",
+    "chan",
+    "spawn",
+    "config",
+    "next",
+    "init",
+    "test",
+    "quickcheck",
+    "assert_eq",
+    "trace_fmt!",
+    "true",
+    "false",
+    "u1",
+    "u8",
+    "u16",
+    "u32",
+    "u64",
+    "s8",
+    "s16",
+    "s32",
+    "s64",
+    "uN",
+    "sN",
+    "bits",
+];
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Wraps DSLX keywords, numeric literals and `//` comments in `<span>`s.
+fn highlight_dslx(code: &str) -> String {
+    let token_re = Regex::new(r"//.*|\b0[xb][0-9a-fA-F_]+\b|\b\d+\b|\b\w+!?").unwrap();
+    let mut out = String::new();
+    for line in code.lines() {
+        let mut last = 0;
+        for m in token_re.find_iter(line) {
+            out.push_str(&escape(&line[last..m.start()]));
+            let token = m.as_str();
+            let class = if token.starts_with("//") {
+                Some("cmt")
+            } else if token.starts_with(|c: char| c.is_ascii_digit()) {
+                Some("num")
+            } else if DSLX_KEYWORDS.contains(&token) {
+                Some("kw")
+            } else {
+                None
+            };
+            match class {
+                Some(class) => out.push_str(&format!(
+                    "<span class=\"{}\">{}</span>",
+                    class,
+                    escape(token)
+                )),
+                None => out.push_str(&escape(token)),
+            }
+            last = m.end();
+        }
+        out.push_str(&escape(&line[last..]));
+        out.push('\n');
+    }
+    out
+}
+
+fn details(summary: &str, body: &str, open: bool) -> String {
+    format!(
+        "<details{}><summary>{}</summary><pre>{}</pre></details>\n",
+        if open { " open" } else { "" },
+        escape(summary),
+        body
+    )
+}
+
+/// Renders the current build -- source, every artifact, test output and per
+/// function op counts -- as a single HTML page with inline CSS only.
+pub(crate) fn generate_html_report(app: &App) -> String {
+    let file = app.file_path.as_deref().unwrap_or("");
+    let timestamp = app
+        .last_update
+        .unwrap_or_else(chrono::Local::now)
+        .format(&app.time_format)
+        .to_string();
+    let status = match &app.error_message {
+        Some(_) => "<span class=\"err\">build failed</span>",
+        None => "<span class=\"ok\">build succeeded</span>",
+    };
+
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str(&format!("<title>{} build report</title>\n", escape(file)));
+    html.push_str(&format!("<style>{}</style>\n</head>\n<body>\n", STYLE));
+    html.push_str(&format!("<h1>{}</h1>\n", escape(file)));
+    html.push_str(&format!(
+        "<p class=\"meta\">Built at {} &mdash; {}</p>\n",
+        escape(&timestamp),
+        status
+    ));
+
+    html.push_str(&details("DSLX source", &highlight_dslx(&app.code), true));
+    if let Some(error) = &app.error_message {
+        html.push_str(&details("Error", &escape(error), true));
+    }
+    html.push_str(&details("Unoptimized IR", &escape(&app.unopt_ir), false));
+    html.push_str(&details("Optimized IR", &escape(&app.opt_ir), false));
+    html.push_str(&details("Delay info", &escape(&app.delay_info), false));
+    if let Some(output) = &app.test_output {
+        html.push_str(&details("Test output", &escape(output), false));
+    }
+
+    let op_counts = ir_op_counts(&app.unopt_ir);
+    html.push_str("<h2>Statistics</h2>\n<table>\n<tr><th>Function</th><th>Ops (unopt)</th></tr>\n");
+    for entry in &app.entry_points {
+        html.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td></tr>\n",
+            escape(entry),
+            op_counts.get(entry).copied().unwrap_or(0)
+        ));
+    }
+    html.push_str("</table>\n</body>\n</html>\n");
+    html
+}
@@ -0,0 +1,129 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Parsing of the pipeline schedule `codegen_main` writes with
+//! `--output_schedule_path` into a per-stage breakdown.
+
+use std::collections::HashMap;
+
+use regex::Regex;
+
+use crate::ir::{ir_nodes, node_bits};
+
+/// One pipeline stage of a schedule.
+#[derive(Clone, Debug)]
+pub(crate) struct StageSchedule {
+    pub(crate) stage: usize,
+    pub(crate) nodes: usize,
+    // Longest path through the stage's nodes.
+    pub(crate) delay_ps: u64,
+    // Bits of values registered at the end of the stage for later stages;
+    // zero for the last stage.
+    pub(crate) register_bits: u64,
+}
+
+/// The schedule of the function codegen pipelined.
+#[derive(Clone, Debug)]
+pub(crate) struct PipelineSchedule {
+    pub(crate) stages: Vec<StageSchedule>,
+    // The clock period the schedule achieves: its slowest stage.
+    pub(crate) clock_period_ps: u64,
+}
+
+impl PipelineSchedule {
+    /// The breakdown shown above the generated Verilog, as Verilog comments.
+    pub(crate) fn table(&self) -> String {
+        let mut lines = vec![format!(
+            "// {:>5} {:>6} {:>9} {:>10}",
+            "stage", "nodes", "delay", "registers"
+        )];
+        for stage in &self.stages {
+            lines.push(format!(
+                "// {:>5} {:>6} {:>7}ps {:>5} bits",
+                stage.stage, stage.nodes, stage.delay_ps, stage.register_bits
+            ));
+        }
+        lines.push(format!(
+            "// {} stage{} at a {}ps clock period",
+            self.stages.len(),
+            if self.stages.len() == 1 { "" } else { "s" },
+            self.clock_period_ps
+        ));
+        lines.join("\n")
+    }
+}
+
+/// Parses a schedule textproto such as
+/// `stages { stage: 0 timed_nodes { node: "add.3" path_delay_ps: 120 } }`,
+/// nested in a package schedule or not, taking each node's widths and users
+/// from `opt_ir`. `None` when no stage is found, so unexpected output leaves
+/// the Verilog tab as it was.
+pub(crate) fn parse_schedule(textproto: &str, opt_ir: &str) -> Option<PipelineSchedule> {
+    let field_re =
+        Regex::new(r#"^\s*(stage|node|path_delay_ps|min_clock_period_ps): "?([^"]*)"?\s*$"#)
+            .unwrap();
+    // Stage of each node and the path delay up to it.
+    let mut scheduled: HashMap<String, (usize, u64)> = HashMap::new();
+    let mut stage_count = 0;
+    let mut min_clock_period_ps = None;
+    let (mut stage, mut node) = (None, None);
+    for cap in textproto.lines().filter_map(|line| field_re.captures(line)) {
+        match &cap[1] {
+            "stage" => {
+                stage = cap[2].parse::<usize>().ok();
+                stage_count = stage_count.max(stage.map_or(0, |s| s + 1));
+            }
+            "node" => node = Some(cap[2].to_string()),
+            "path_delay_ps" => {
+                if let (Some(stage), Some(node)) = (stage, node.take()) {
+                    scheduled.insert(node, (stage, cap[2].parse().unwrap_or(0)));
+                }
+            }
+            _ => min_clock_period_ps = cap[2].parse::<u64>().ok(),
+        }
+    }
+    if stage_count == 0 {
+        return None;
+    }
+    let mut stages: Vec<StageSchedule> = (0..stage_count)
+        .map(|stage| StageSchedule {
+            stage,
+            nodes: 0,
+            delay_ps: 0,
+            register_bits: 0,
+        })
+        .collect();
+    for (stage, path_delay_ps) in scheduled.values() {
+        stages[*stage].nodes += 1;
+        stages[*stage].delay_ps = stages[*stage].delay_ps.max(*path_delay_ps);
+    }
+    // A value is registered at the end of every stage from its own up to
+    // the last one using it.
+    let bits = node_bits(opt_ir);
+    let functions = ir_nodes(opt_ir);
+    let mut last_use: HashMap<&str, usize> = HashMap::new();
+    for node in functions.iter().flat_map(|(_, nodes)| nodes) {
+        let Some((user_stage, _)) = scheduled.get(&node.name) else {
+            continue;
+        };
+        for operand in &node.operands {
+            let last = last_use.entry(operand.as_str()).or_insert(*user_stage);
+            *last = (*last).max(*user_stage);
+        }
+    }
+    for (name, last) in last_use {
+        let (Some((def_stage, _)), Some(width)) = (scheduled.get(name), bits.get(name)) else {
+            continue;
+        };
+        if let Some(crossed) = stages.get_mut(*def_stage..last) {
+            for stage in crossed {
+                stage.register_bits += width;
+            }
+        }
+    }
+    let clock_period_ps =
+        min_clock_period_ps.unwrap_or_else(|| stages.iter().map(|s| s.delay_ps).max().unwrap_or(0));
+    Some(PipelineSchedule {
+        stages,
+        clock_period_ps,
+    })
+}
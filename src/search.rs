@@ -0,0 +1,126 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Search within the results pane.
+
+use regex::{Regex, RegexBuilder};
+use std::borrow::Cow;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// A match as (line index, byte start, byte end) within that line.
+pub(crate) type Match = (usize, usize, usize);
+
+/// Query prefix switching from a literal search to a regular expression.
+pub(crate) const REGEX_PREFIX: &str = "re:";
+
+/// The active search: query, options and the precomputed match positions.
+#[derive(Default)]
+pub(crate) struct Search {
+    pub(crate) query: String,
+    pub(crate) case_sensitive: bool,
+    pub(crate) whole_word: bool,
+    // True while the query is still being typed at the `/` prompt.
+    pub(crate) editing: bool,
+    pub(crate) matches: Vec<Match>,
+    // Index into `matches` of the match n/N last moved to.
+    pub(crate) current: usize,
+    // Set when the query is a regex that does not compile; nothing matches.
+    pub(crate) invalid_regex: bool,
+    // Hash of the query, options and searched text the matches belong to.
+    key: Option<u64>,
+}
+
+impl Search {
+    pub(crate) fn new() -> Self {
+        Search {
+            editing: true,
+            ..Search::default()
+        }
+    }
+
+    /// Recomputes the matches against `text` if the query, the options or
+    /// the text changed since the last call.
+    pub(crate) fn update(&mut self, text: &str) {
+        let mut hasher = DefaultHasher::new();
+        (&self.query, self.case_sensitive, self.whole_word, text).hash(&mut hasher);
+        let key = hasher.finish();
+        if self.key == Some(key) {
+            return;
+        }
+        self.key = Some(key);
+        let (query, regex) = match self.query.strip_prefix(REGEX_PREFIX) {
+            Some(pattern) => (pattern, true),
+            None => (self.query.as_str(), false),
+        };
+        let re = search_regex(query, regex, self.case_sensitive, self.whole_word);
+        self.invalid_regex = re.is_none();
+        self.matches = match re {
+            Some(re) if !query.is_empty() => find_matches(text, &re),
+            _ => Vec::new(),
+        };
+        self.current = 0;
+    }
+
+    /// Whether the query is a regular expression rather than literal text.
+    pub(crate) fn is_regex(&self) -> bool {
+        self.query.starts_with(REGEX_PREFIX)
+    }
+
+    pub(crate) fn current_match(&self) -> Option<Match> {
+        self.matches.get(self.current).copied()
+    }
+
+    pub(crate) fn next(&mut self) {
+        if !self.matches.is_empty() {
+            self.current = (self.current + 1) % self.matches.len();
+        }
+    }
+
+    pub(crate) fn prev(&mut self) {
+        if !self.matches.is_empty() {
+            self.current = (self.current + self.matches.len() - 1) % self.matches.len();
+        }
+    }
+
+    /// "3/12" style position, or "0/0" when nothing matches.
+    pub(crate) fn position(&self) -> String {
+        if self.matches.is_empty() {
+            String::from("0/0")
+        } else {
+            format!("{}/{}", self.current + 1, self.matches.len())
+        }
+    }
+}
+
+/// The regex for `query`: escaped unless `regex`, in which case `None` is
+/// returned if it does not compile.
+fn search_regex(query: &str, regex: bool, case_sensitive: bool, whole_word: bool) -> Option<Regex> {
+    let pattern = if regex {
+        Cow::Borrowed(query)
+    } else {
+        Cow::Owned(regex::escape(query))
+    };
+    let pattern = if whole_word {
+        format!(r"\b(?:{})\b", pattern)
+    } else {
+        pattern.into_owned()
+    };
+    RegexBuilder::new(&pattern)
+        .case_insensitive(!case_sensitive)
+        .build()
+        .ok()
+}
+
+/// Every match of `re` in `text`, line by line. Empty matches, which a
+/// regex such as `a*` finds everywhere, are skipped.
+pub(crate) fn find_matches(text: &str, re: &Regex) -> Vec<Match> {
+    text.lines()
+        .enumerate()
+        .flat_map(|(i, line)| {
+            re.find_iter(line)
+                .filter(|m| !m.is_empty())
+                .map(move |m| (i, m.start(), m.end()))
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
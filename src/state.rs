@@ -0,0 +1,57 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! The view state remembered between sessions on the same file: selected tab
+//! and entry, delay model and scroll positions, kept under
+//! `$XDG_STATE_HOME/dslx-watch-tui/`.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// What is saved on exit and restored at startup. Tabs are keyed by stage
+/// name (`unopt`, `opt`, ...) so the file survives tabs being added.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct SavedState {
+    pub(crate) selected_tab: String,
+    pub(crate) selected_entry: usize,
+    pub(crate) delay_model: String,
+    pub(crate) tab_scrolls: HashMap<String, usize>,
+    // Hashes of the content each IR and delay tab's scroll position refers
+    // to, so a position is only kept while that content is unchanged.
+    pub(crate) scrolled_content: Vec<u64>,
+}
+
+/// The state file for the watched `file`, named by a hash of its canonical
+/// path; `None` when neither `XDG_STATE_HOME` nor `HOME` is set.
+fn state_path(file: &Path) -> Option<PathBuf> {
+    let state_home = match std::env::var_os("XDG_STATE_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(std::env::var_os("HOME")?).join(".local/state"),
+    };
+    let canonical = fs::canonicalize(file).unwrap_or_else(|_| file.to_path_buf());
+    let hash = crate::app::fnv1a_hash(&canonical.to_string_lossy());
+    Some(
+        state_home
+            .join("dslx-watch-tui")
+            .join(format!("{:016x}.json", hash)),
+    )
+}
+
+/// The state saved for `file`; `None` if there is none or it is unreadable.
+pub(crate) fn load_state(file: &Path) -> Option<SavedState> {
+    let contents = fs::read_to_string(state_path(file)?).ok()?;
+    serde_json::from_str(&contents).ok()
+}
+
+/// Saves `state` for `file`. Failures are ignored: the state is a
+/// convenience, and the session is ending anyway.
+pub(crate) fn save_state(file: &Path, state: &SavedState) {
+    let Some(path) = state_path(file) else {
+        return;
+    };
+    if let (Some(dir), Ok(json)) = (path.parent(), serde_json::to_string(state)) {
+        let _ = fs::create_dir_all(dir).and_then(|()| fs::write(&path, json));
+    }
+}
@@ -0,0 +1,174 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Color schemes for the TUI.
+
+use ratatui::style::{Color, Modifier, Style};
+
+/// Styles for every colored element of the UI, selected with `--color-scheme`.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct Theme {
+    pub tab_title: Style,
+    pub badge: Style,
+    pub selected: Style,
+    pub muted: Style,
+    pub placeholder: Style,
+    pub success: Style,
+    pub warning: Style,
+    pub error: Style,
+    pub status_ok: Style,
+    pub status_err: Style,
+    pub cursor_line: Style,
+    pub error_line: Style,
+    pub key_hint: Style,
+    pub scrollbar_thumb: Style,
+    pub scrollbar_track: Style,
+    pub diff_added: Style,
+    pub diff_removed: Style,
+    pub diff_changed: Style,
+    pub search_match: Style,
+    pub search_current: Style,
+}
+
+impl Theme {
+    /// The full-color scheme; the cursor line uses an RGB background.
+    pub fn default_colors() -> Theme {
+        Theme {
+            cursor_line: Style::default().bg(Color::Rgb(30, 40, 70)),
+            error_line: Style::default().bg(Color::Rgb(100, 20, 20)),
+            ..Theme::ansi16()
+        }
+    }
+
+    /// Restricted to the basic 16-color ANSI palette.
+    pub fn ansi16() -> Theme {
+        Theme {
+            tab_title: Style::default().fg(Color::Yellow),
+            badge: Style::default().fg(Color::Cyan),
+            selected: Style::default().fg(Color::LightGreen),
+            muted: Style::default().fg(Color::DarkGray),
+            placeholder: Style::default().fg(Color::Gray),
+            success: Style::default().fg(Color::Green),
+            warning: Style::default().fg(Color::Yellow),
+            error: Style::default().fg(Color::Red),
+            status_ok: Style::default().bg(Color::Green).fg(Color::Black),
+            status_err: Style::default().bg(Color::Red).fg(Color::Black),
+            cursor_line: Style::default().bg(Color::Blue),
+            error_line: Style::default().bg(Color::Red).fg(Color::Black),
+            key_hint: Style::default().fg(Color::Black).bg(Color::Gray),
+            scrollbar_thumb: Style::default().fg(Color::Gray),
+            scrollbar_track: Style::default().fg(Color::DarkGray),
+            diff_added: Style::default().fg(Color::Green),
+            diff_removed: Style::default().fg(Color::Red),
+            diff_changed: Style::default().fg(Color::Yellow),
+            search_match: Style::default().fg(Color::Black).bg(Color::Yellow),
+            search_current: Style::default().fg(Color::Black).bg(Color::LightRed),
+        }
+    }
+
+    /// Monochrome: no colors at all, only reverse video where an element
+    /// would otherwise be invisible.
+    pub fn none() -> Theme {
+        let plain = Style::default();
+        let reversed = Style::default().add_modifier(Modifier::REVERSED);
+        Theme {
+            tab_title: plain,
+            badge: plain,
+            selected: reversed,
+            muted: plain,
+            placeholder: plain,
+            success: plain,
+            warning: plain,
+            error: plain,
+            status_ok: reversed,
+            status_err: reversed,
+            cursor_line: reversed,
+            error_line: Style::default().add_modifier(Modifier::UNDERLINED),
+            key_hint: reversed,
+            scrollbar_thumb: plain,
+            scrollbar_track: plain,
+            diff_added: plain,
+            diff_removed: plain,
+            diff_changed: plain,
+            search_match: Style::default().add_modifier(Modifier::UNDERLINED),
+            search_current: reversed,
+        }
+    }
+
+    /// Recolors the element named like its field (e.g. `badge`) with a color
+    /// name (`red`, `lightblue`, ...) or `#rrggbb`. The color replaces the
+    /// element's background where it has one, its foreground otherwise.
+    pub fn set_color(&mut self, element: &str, color: &str) -> Result<(), String> {
+        let color = parse_color(color).ok_or_else(|| format!("unknown color '{}'", color))?;
+        let style = match element {
+            "tab_title" => &mut self.tab_title,
+            "badge" => &mut self.badge,
+            "selected" => &mut self.selected,
+            "muted" => &mut self.muted,
+            "placeholder" => &mut self.placeholder,
+            "success" => &mut self.success,
+            "warning" => &mut self.warning,
+            "error" => &mut self.error,
+            "status_ok" => &mut self.status_ok,
+            "status_err" => &mut self.status_err,
+            "cursor_line" => &mut self.cursor_line,
+            "error_line" => &mut self.error_line,
+            "key_hint" => &mut self.key_hint,
+            "scrollbar_thumb" => &mut self.scrollbar_thumb,
+            "scrollbar_track" => &mut self.scrollbar_track,
+            "diff_added" => &mut self.diff_added,
+            "diff_removed" => &mut self.diff_removed,
+            "diff_changed" => &mut self.diff_changed,
+            "search_match" => &mut self.search_match,
+            "search_current" => &mut self.search_current,
+            _ => return Err(format!("unknown theme element '{}'", element)),
+        };
+        if style.bg.is_some() {
+            style.bg = Some(color);
+        } else {
+            style.fg = Some(color);
+        }
+        Ok(())
+    }
+
+    pub fn from_name(name: &str) -> Option<Theme> {
+        match name {
+            "default" => Some(Theme::default_colors()),
+            "ansi16" => Some(Theme::ansi16()),
+            "none" => Some(Theme::none()),
+            _ => None,
+        }
+    }
+}
+
+/// A color by ANSI name, in lowercase without separators, or as `#rrggbb`.
+fn parse_color(name: &str) -> Option<Color> {
+    if let Some(hex) = name.strip_prefix('#').filter(|hex| hex.len() == 6) {
+        let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+        return Some(Color::Rgb(channel(0)?, channel(2)?, channel(4)?));
+    }
+    let color = match name
+        .to_ascii_lowercase()
+        .replace(['_', '-', ' '], "")
+        .as_str()
+    {
+        "black" => Color::Black,
+        "red" => Color::Red,
+        "green" => Color::Green,
+        "yellow" => Color::Yellow,
+        "blue" => Color::Blue,
+        "magenta" => Color::Magenta,
+        "cyan" => Color::Cyan,
+        "gray" | "grey" => Color::Gray,
+        "darkgray" | "darkgrey" => Color::DarkGray,
+        "lightred" => Color::LightRed,
+        "lightgreen" => Color::LightGreen,
+        "lightyellow" => Color::LightYellow,
+        "lightblue" => Color::LightBlue,
+        "lightmagenta" => Color::LightMagenta,
+        "lightcyan" => Color::LightCyan,
+        "white" => Color::White,
+        _ => return None,
+    };
+    Some(color)
+}
@@ -0,0 +1,231 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Invocation of the XLS toolchain binaries.
+
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// Binaries the build pipeline cannot run without.
+pub const REQUIRED_TOOLS: &[&str] = &["ir_converter_main", "opt_main", "delay_info_main"];
+
+/// Runs the DSLX tests; skipped when absent.
+const INTERPRETER_TOOL: &str = "dslx_interpreter_main";
+
+/// Generates the Verilog tab; skipped when absent.
+pub(crate) const CODEGEN_TOOL: &str = "codegen_main";
+
+/// Checks the opt IR against the unopt IR for the equivalence tab; skipped
+/// when absent.
+pub(crate) const EQUIVALENCE_TOOL: &str = "check_ir_equivalence_main";
+
+/// Runs XLS tools on behalf of the build pipeline. Embedders can supply their
+/// own implementation, e.g. to serve canned outputs in tests.
+pub trait ToolRunner: Send {
+    /// Runs `tool` (a binary name such as `opt_main`) with `args`.
+    fn run(&self, tool: &str, args: &[String]) -> io::Result<Output>;
+
+    /// Whether `tool` is available; optional stages are skipped when not.
+    fn has_tool(&self, tool: &str) -> bool;
+
+    /// Directory the binaries are loaded from, if any, so it can be watched
+    /// for rebuilt tools.
+    fn tools_dir(&self) -> Option<&Path> {
+        None
+    }
+
+    /// Tools given an explicit path. Those must be present even when
+    /// optional, since a bad path would otherwise silently skip the stage.
+    fn tool_overrides(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+/// Whether `name` is one of the binaries the build pipeline runs.
+pub(crate) fn is_known_tool(name: &str) -> bool {
+    REQUIRED_TOOLS.contains(&name)
+        || [INTERPRETER_TOOL, CODEGEN_TOOL, EQUIVALENCE_TOOL].contains(&name)
+}
+
+/// Required or explicitly pathed binaries `runner` cannot currently run.
+pub(crate) fn missing_tools(runner: &dyn ToolRunner) -> Vec<String> {
+    let mut tools: Vec<String> = REQUIRED_TOOLS.iter().map(|tool| tool.to_string()).collect();
+    let mut overrides = runner.tool_overrides();
+    overrides.sort();
+    for tool in overrides {
+        if !tools.contains(&tool) {
+            tools.push(tool);
+        }
+    }
+    tools.retain(|tool| !runner.has_tool(tool));
+    tools
+}
+
+/// Runs the real binaries from an xlsynth tools directory.
+#[derive(Clone, Debug)]
+pub struct XlsynthToolRunner {
+    tools_dir: PathBuf,
+    // Binaries run instead of those in `tools_dir`, by tool name.
+    tool_paths: HashMap<String, PathBuf>,
+    // Directory the tools run in; ours when `None`.
+    workdir: Option<PathBuf>,
+}
+
+impl XlsynthToolRunner {
+    pub fn new(tools_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            tools_dir: tools_dir.into(),
+            tool_paths: HashMap::new(),
+            workdir: None,
+        }
+    }
+
+    /// Runs the tools in `workdir`, so relative imports in the DSLX source
+    /// resolve against it.
+    pub fn with_workdir(mut self, workdir: impl Into<PathBuf>) -> Self {
+        // A relative tools directory must still resolve against ours.
+        if let Ok(tools_dir) = std::path::absolute(&self.tools_dir) {
+            self.tools_dir = tools_dir;
+        }
+        self.workdir = Some(workdir.into());
+        self
+    }
+
+    /// Runs `path` whenever `tool` is asked for, e.g. a locally built
+    /// `opt_main` alongside the released other tools.
+    pub fn with_tool_path(mut self, tool: impl Into<String>, path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        // Like the tools directory, resolved against ours, not the workdir.
+        let path = std::path::absolute(&path).unwrap_or(path);
+        self.tool_paths.insert(tool.into(), path);
+        self
+    }
+
+    /// Uses the directory named by `XLSYNTH_TOOLS`, checking that every
+    /// required binary is present. The error explains what to fix.
+    pub fn from_env() -> Result<Self, String> {
+        Self::from_env_with_tool_paths(&HashMap::new())
+    }
+
+    /// Like [`XlsynthToolRunner::from_env`], but runs the binaries in
+    /// `tool_paths` instead of the directory's; the check is of the paths
+    /// actually run, and covers every tool in `tool_paths`.
+    pub fn from_env_with_tool_paths(tool_paths: &HashMap<String, PathBuf>) -> Result<Self, String> {
+        let tools = std::env::var_os("XLSYNTH_TOOLS")
+            .filter(|dir| !dir.is_empty())
+            .ok_or_else(|| {
+                String::from(
+                    "XLSYNTH_TOOLS is not set; set it to the directory holding the xlsynth \
+                     tool binaries, e.g. `export XLSYNTH_TOOLS=$HOME/opt/xlsynth/latest`",
+                )
+            })?;
+        let runner = tool_paths
+            .iter()
+            .fold(Self::new(tools), |runner, (tool, path)| {
+                runner.with_tool_path(tool, path)
+            });
+        let missing: Vec<String> = missing_tools(&runner)
+            .iter()
+            .map(|tool| {
+                format!(
+                    "  {} (looked for {})",
+                    tool,
+                    runner.tool_path(tool).display()
+                )
+            })
+            .collect();
+        if !missing.is_empty() {
+            return Err(format!(
+                "xlsynth tools are missing or not executable:\n{}\n\
+                 Point XLSYNTH_TOOLS at a complete xlsynth tools directory, or pass the \
+                 tool's --*-path flag.",
+                missing.join("\n")
+            ));
+        }
+        Ok(runner)
+    }
+
+    fn tool_path(&self, tool: &str) -> PathBuf {
+        match self.tool_paths.get(tool) {
+            Some(path) => path.clone(),
+            None => self.tools_dir.join(tool),
+        }
+    }
+}
+
+impl ToolRunner for XlsynthToolRunner {
+    fn run(&self, tool: &str, args: &[String]) -> io::Result<Output> {
+        let mut command = Command::new(self.tool_path(tool));
+        if let Some(workdir) = &self.workdir {
+            command.current_dir(workdir);
+        }
+        command.args(args).output()
+    }
+
+    fn has_tool(&self, tool: &str) -> bool {
+        is_executable(&self.tool_path(tool))
+    }
+
+    fn tools_dir(&self) -> Option<&Path> {
+        Some(&self.tools_dir)
+    }
+
+    fn tool_overrides(&self) -> Vec<String> {
+        self.tool_paths.keys().cloned().collect()
+    }
+}
+
+/// A runner shared by the UI thread and background builds. Tool runs are
+/// serialized, since a `ToolRunner` need not be `Sync`.
+#[derive(Clone)]
+pub(crate) struct SharedToolRunner {
+    runner: Arc<Mutex<Box<dyn ToolRunner>>>,
+    // Fixed for the runner's lifetime, so it can be handed out unlocked.
+    tools_dir: Option<PathBuf>,
+    tool_overrides: Vec<String>,
+}
+
+impl SharedToolRunner {
+    pub(crate) fn new(runner: Box<dyn ToolRunner>) -> Self {
+        Self {
+            tools_dir: runner.tools_dir().map(Path::to_path_buf),
+            tool_overrides: runner.tool_overrides(),
+            runner: Arc::new(Mutex::new(runner)),
+        }
+    }
+}
+
+impl ToolRunner for SharedToolRunner {
+    fn run(&self, tool: &str, args: &[String]) -> io::Result<Output> {
+        let runner = self.runner.lock().unwrap_or_else(PoisonError::into_inner);
+        runner.run(tool, args)
+    }
+
+    fn has_tool(&self, tool: &str) -> bool {
+        let runner = self.runner.lock().unwrap_or_else(PoisonError::into_inner);
+        runner.has_tool(tool)
+    }
+
+    fn tools_dir(&self) -> Option<&Path> {
+        self.tools_dir.as_deref()
+    }
+
+    fn tool_overrides(&self) -> Vec<String> {
+        self.tool_overrides.clone()
+    }
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .map(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
@@ -0,0 +1,526 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! The interactive terminal session: watcher setup, event loop and key
+//! handling.
+
+use crate::app::{App, Focus};
+use crate::config::Config;
+use crate::search::Search;
+use crate::state::{load_state, save_state};
+use crate::tools::{is_known_tool, ToolRunner, XlsynthToolRunner};
+use crate::ui;
+use crate::watch::{
+    start_notify_watcher, start_polling_watcher, ImportWatch, StaleWatchCheck, WatcherKind,
+};
+use crossterm::{
+    event::{
+        self, DisableMouseCapture, EnableMouseCapture, KeyCode, KeyEvent, KeyModifiers,
+        MouseButton, MouseEvent, MouseEventKind,
+    },
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use notify::EventKind;
+use ratatui::{backend::CrosstermBackend, layout::Rect, Terminal};
+use std::error::Error;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::channel;
+use std::time::{Duration, Instant};
+
+/// Runs the TUI for `config` using the xlsynth tools named by the
+/// `XLSYNTH_TOOLS` environment variable.
+pub fn run_tui(config: Config) -> Result<(), Box<dyn Error>> {
+    let runner = XlsynthToolRunner::from_env_with_tool_paths(&config.tool_paths)?
+        .with_workdir(config.tool_workdir());
+    run_tui_with_runner(config, Box::new(runner))
+}
+
+/// Runs the TUI for `config`, invoking tools through `tool_runner`.
+pub fn run_tui_with_runner(
+    config: Config,
+    tool_runner: Box<dyn ToolRunner>,
+) -> Result<(), Box<dyn Error>> {
+    let mut app = App::from_config(&config, tool_runner)?;
+    app.background_builds = true;
+    if let Some(state) = load_state(&config.file).filter(|_| !config.no_state) {
+        app.restore_state(state);
+    }
+    let files: Vec<PathBuf> = std::iter::once(&config.file)
+        .chain(&config.extra_files)
+        .cloned()
+        .collect();
+    // Event paths are absolute, and may differ from the given ones by links.
+    let canonical_files: Vec<PathBuf> = files.iter().map(|f| canonical(f)).collect();
+
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    let backend = CrosstermBackend::new(stdout);
+    let mut terminal = Terminal::new(backend)?;
+
+    let (tx, rx) = channel();
+    // Held for the lifetime of the loop; dropping them stops notify events.
+    // Every file is polled instead if any cannot be watched natively.
+    let watchers: Option<Vec<_>> = if config.force_poll {
+        None
+    } else {
+        files
+            .iter()
+            .map(|file| start_notify_watcher(&file.to_string_lossy(), tx.clone()).ok())
+            .collect()
+    };
+    app.watcher_kind = if watchers.is_some() {
+        WatcherKind::Notify
+    } else {
+        for file in &files {
+            start_polling_watcher(file.clone(), config.poll_interval, tx.clone());
+        }
+        WatcherKind::Polling
+    };
+
+    // Picks up rebuilt binaries when developing the XLS tools themselves.
+    let (tools_tx, tools_rx) = channel();
+    let tools_watcher = app
+        .tools_dir()
+        .and_then(|dir| start_notify_watcher(&dir.to_string_lossy(), tools_tx).ok());
+
+    // Imported modules of the active file, refreshed after each build that
+    // got past IR conversion and whenever another file becomes active.
+    let workdir = config.tool_workdir();
+    let mut imports = ImportWatch::new(app.watcher_kind, config.poll_interval);
+    imports.set_files(app.imported_files(&workdir));
+    let mut was_building = false;
+    let mut imports_file = app.active_file;
+
+    let mut stale_checks: Vec<StaleWatchCheck> =
+        files.iter().cloned().map(StaleWatchCheck::new).collect();
+    // The active file's latest change event not yet acted on, and which file
+    // was active then.
+    let mut pending_change: Option<(usize, Instant)> = None;
+    // The latest tool binary change not yet acted on. Relinking a tool fires
+    // a burst of events, which are debounced like source changes.
+    let mut pending_tools_change: Option<Instant> = None;
+
+    app.check_and_run_conversion();
+
+    loop {
+        app.poll_build();
+        app.poll_on_build(false);
+        app.poll_reduce();
+        app.poll_equivalence();
+        terminal.draw(|f| ui::draw(f, &mut app))?;
+
+        // Drains every queued event, not just up to the first tool change.
+        let tool_events = tools_rx
+            .try_iter()
+            .filter(|event| match event {
+                Ok(event) => {
+                    matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_))
+                        && event.paths.iter().any(|path| {
+                            path.file_name()
+                                .is_some_and(|name| is_known_tool(&name.to_string_lossy()))
+                        })
+                }
+                Err(_) => false,
+            })
+            .count();
+        if tools_watcher.is_some() && tool_events > 0 {
+            pending_tools_change = Some(Instant::now());
+        }
+        if pending_tools_change.is_some_and(|at| at.elapsed() >= config.debounce) {
+            pending_tools_change = None;
+            app.show_toast(String::from("Tools updated, rebuilding…"));
+            terminal.draw(|f| ui::draw(f, &mut app))?;
+            app.rebuild_with_updated_tools();
+        }
+
+        // Handle file change events once they have settled for the debounce
+        // interval. Changes to inactive files are only noted.
+        for event in rx.try_iter() {
+            match event {
+                Ok(event) => {
+                    let file = event
+                        .paths
+                        .iter()
+                        .find_map(|path| canonical_files.iter().position(|f| *f == canonical(path)))
+                        .unwrap_or(app.active_file);
+                    stale_checks[file].saw_event();
+                    if !matches!(event.kind, EventKind::Modify(_)) {
+                        continue;
+                    }
+                    if file != app.active_file {
+                        app.mark_file_changed(file);
+                        continue;
+                    }
+                    if pending_change.is_none() {
+                        app.last_change_at = Some(Instant::now());
+                    }
+                    pending_change = Some((file, Instant::now()));
+                }
+                Err(e) => {
+                    app.watch_warning =
+                        Some(format!("file watching failed ({}); restart with --poll", e));
+                }
+            }
+        }
+        if let Some(path) = imports.changed_file() {
+            app.import_changed(&path);
+            if pending_change.is_none() {
+                app.last_change_at = Some(Instant::now());
+            }
+            pending_change = Some((app.active_file, Instant::now()));
+        }
+        let converted = !app
+            .stage_errors
+            .iter()
+            .any(|(stage, _)| stage == "ir_converter_main");
+        if (was_building && !app.is_building() && converted) || imports_file != app.active_file {
+            imports.set_files(app.imported_files(&workdir));
+            imports_file = app.active_file;
+        }
+        was_building = app.is_building();
+
+        let mut changed = false;
+        if let Some((file, at)) = pending_change {
+            if at.elapsed() >= config.debounce {
+                pending_change = None;
+                if file == app.active_file {
+                    changed = true;
+                } else {
+                    app.mark_file_changed(file);
+                }
+            }
+        }
+        for (file, stale_check) in stale_checks.iter_mut().enumerate() {
+            if app.watcher_kind != WatcherKind::Notify || !stale_check.is_stale() {
+                continue;
+            }
+            app.watch_warning = Some(String::from(
+                "file changed without a watch event; restart with --poll",
+            ));
+            stale_check.saw_event();
+            if file == app.active_file {
+                app.last_change_at = Some(Instant::now());
+                changed = true;
+            } else {
+                app.mark_file_changed(file);
+            }
+        }
+        if changed {
+            if app.paused {
+                app.note_paused_change();
+            } else {
+                app.reload_and_rebuild();
+            }
+        }
+
+        // Handle keyboard events for tab switching and exit
+        if event::poll(Duration::from_millis(50))? {
+            match event::read()? {
+                event::Event::Key(key_event) if handle_key(&mut app, key_event) => break,
+                event::Event::Mouse(mouse_event) => handle_mouse(&mut app, mouse_event),
+                _ => {}
+            }
+        }
+    }
+
+    if let (false, Some(file)) = (config.no_state, &app.file_path) {
+        save_state(Path::new(file), &app.saved_state());
+    }
+
+    disable_raw_mode()?;
+    execute!(
+        terminal.backend_mut(),
+        LeaveAlternateScreen,
+        DisableMouseCapture
+    )?;
+    terminal.show_cursor()?;
+    Ok(())
+}
+
+/// `path` with links and relative components resolved, or unchanged if it
+/// cannot be (e.g. while an editor is replacing the file).
+fn canonical(path: &Path) -> PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Columns moved by Left/Right when the results pane has focus.
+const HSCROLL_COLUMNS: usize = 8;
+
+/// Results lines scrolled per mouse wheel step.
+const WHEEL_LINES: usize = 3;
+
+/// Applies a click or scroll to `app`: a click selects the tab or entry
+/// under it, and the wheel scrolls the results pane.
+fn handle_mouse(app: &mut App, mouse_event: MouseEvent) {
+    if app.show_help {
+        return;
+    }
+    let (column, row) = (mouse_event.column, mouse_event.row);
+    let areas = &app.mouse_areas;
+    match mouse_event.kind {
+        MouseEventKind::ScrollUp => {
+            app.results_scroll = app.results_scroll.saturating_sub(WHEEL_LINES);
+        }
+        MouseEventKind::ScrollDown => {
+            // Clamped against the content length at draw time.
+            app.results_scroll += WHEEL_LINES;
+        }
+        MouseEventKind::Down(MouseButton::Left) => {
+            if let Some((_, tab)) = areas
+                .tabs
+                .iter()
+                .find(|(area, _)| contains(*area, column, row))
+            {
+                app.select_tab(*tab);
+            } else if contains(areas.entries, column, row) {
+                let entry = areas.first_entry + (row - areas.entries.y) as usize;
+                if entry < app.entry_points.len() && entry != app.selected_entry {
+                    app.push_nav_undo();
+                    app.selected_entry = entry;
+                    app.check_and_run_conversion();
+                }
+            } else if contains(areas.results, column, row) {
+                app.focus = Focus::Results;
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Whether the cell at `column`, `row` is within `area`.
+fn contains(area: Rect, column: u16, row: u16) -> bool {
+    (area.x..area.right()).contains(&column) && (area.y..area.bottom()).contains(&row)
+}
+
+/// Applies a key press to `app`; returns true when the user asked to quit.
+fn handle_key(app: &mut App, key_event: KeyEvent) -> bool {
+    app.opt_ir_changed_at = None;
+    if app.show_help {
+        match key_event.code {
+            // q closes the overlay rather than quitting.
+            KeyCode::Char('?' | 'q') | KeyCode::Esc | KeyCode::F(1) => app.show_help = false,
+            // Clamped against the overlay's length at draw time.
+            KeyCode::Char('j') | KeyCode::Down => app.help_scroll += 1,
+            KeyCode::Char('k') | KeyCode::Up => {
+                app.help_scroll = app.help_scroll.saturating_sub(1);
+            }
+            KeyCode::PageDown => app.help_scroll += app.help_viewport.max(1),
+            KeyCode::PageUp => {
+                app.help_scroll = app.help_scroll.saturating_sub(app.help_viewport.max(1));
+            }
+            _ => {}
+        }
+        return false;
+    }
+    if let Some(input) = app.command_input.as_mut() {
+        match key_event.code {
+            KeyCode::Char(c) => input.push(c),
+            KeyCode::Backspace => {
+                input.pop();
+            }
+            KeyCode::Enter => {
+                let command = app.command_input.take().unwrap();
+                app.execute_command(&command);
+            }
+            KeyCode::Esc => app.command_input = None,
+            _ => {}
+        }
+        return false;
+    }
+    if let Some(search) = app.search.as_mut().filter(|s| s.editing) {
+        match key_event.code {
+            KeyCode::Char('c') if key_event.modifiers.contains(KeyModifiers::ALT) => {
+                search.case_sensitive = !search.case_sensitive;
+            }
+            KeyCode::Char('w') if key_event.modifiers.contains(KeyModifiers::ALT) => {
+                search.whole_word = !search.whole_word;
+            }
+            KeyCode::Char(c) => search.query.push(c),
+            KeyCode::Backspace => {
+                search.query.pop();
+            }
+            KeyCode::Enter if search.query.is_empty() => app.search = None,
+            KeyCode::Enter => search.editing = false,
+            KeyCode::Esc => app.search = None,
+            _ => {}
+        }
+        app.scroll_to_search_match();
+        return false;
+    }
+    if let Some(mark) = app.pending_bookmark.take() {
+        if let KeyCode::Char(digit @ '0'..='9') = key_event.code {
+            if mark == 'm' {
+                app.set_bookmark(digit);
+            } else {
+                app.jump_to_bookmark(digit);
+            }
+        }
+        return false;
+    }
+    let count = app.count_prefix.take();
+    match key_event.code {
+        KeyCode::Char(':') => app.command_input = Some(String::new()),
+        KeyCode::Char('/') => app.search = Some(Search::new()),
+        KeyCode::Char('?') | KeyCode::F(1) => {
+            app.show_help = true;
+            app.help_scroll = 0;
+        }
+        KeyCode::Char('n') if app.search.is_some() => {
+            app.update_search();
+            app.search.as_mut().unwrap().next();
+            app.scroll_to_search_match();
+        }
+        KeyCode::Char('N') if app.search.is_some() => {
+            app.update_search();
+            app.search.as_mut().unwrap().prev();
+            app.scroll_to_search_match();
+        }
+        KeyCode::Char(c @ '0'..='9') if !key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+            let digit = c.to_digit(10).unwrap() as usize;
+            app.count_prefix = Some(count.unwrap_or(0).saturating_mul(10) + digit);
+        }
+        KeyCode::Char('g') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+            // Line numbers are shown 1-based.
+            if let Some(line) = count {
+                app.source_cursor_row = line.saturating_sub(1);
+                app.clamp_source_cursor();
+            }
+        }
+        KeyCode::Char('k') => {
+            app.source_cursor_row = app.source_cursor_row.saturating_sub(1);
+        }
+        KeyCode::Char('j') => {
+            app.source_cursor_row += 1;
+            app.clamp_source_cursor();
+        }
+        KeyCode::Tab => app.next_tab(),
+        KeyCode::Char('p') if key_event.modifiers.is_empty() => {
+            app.paused = !app.paused;
+            if !app.paused && app.pending_changes > 0 {
+                app.pending_changes = 0;
+                app.reload_and_rebuild();
+            }
+        }
+        KeyCode::Char('d') if key_event.modifiers.is_empty() => {
+            app.show_delay_diff = !app.show_delay_diff;
+        }
+        KeyCode::Char('D') => app.cycle_delay_model(),
+        KeyCode::Char('+') => app.adjust_pipeline_stages(1),
+        KeyCode::Char('-') => app.adjust_pipeline_stages(-1),
+        KeyCode::PageUp => {
+            app.results_scroll = app
+                .results_scroll
+                .saturating_sub(app.results_viewport.max(1));
+        }
+        KeyCode::PageDown => {
+            // Clamped against the content length at draw time.
+            app.results_scroll += app.results_viewport.max(1);
+        }
+        KeyCode::Char('u') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+            app.select_tab(0);
+        }
+        KeyCode::Char('o') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+            app.select_tab(1);
+        }
+        KeyCode::Char('d') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+            app.select_tab(2);
+        }
+        KeyCode::Char('v') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+            app.select_tab(7);
+        }
+        KeyCode::Char('p') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+            app.select_tab(8);
+        }
+        KeyCode::Char('e') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+            app.select_tab(9);
+        }
+        KeyCode::Char(mark @ ('m' | '\'')) => app.pending_bookmark = Some(mark),
+        KeyCode::Enter => app.toggle_fold(),
+        KeyCode::Char('t') => app.cycle_test_filter(),
+        KeyCode::Char('e') if !app.stage_errors.is_empty() => {
+            app.selected_error = (app.selected_error + 1) % app.stage_errors.len();
+        }
+        KeyCode::Char('i') => {
+            app.inline_opt_diff = !app.inline_opt_diff;
+            app.show_toast(format!(
+                "Inline opt IR diffs {}",
+                if app.inline_opt_diff { "on" } else { "off" }
+            ));
+        }
+        KeyCode::Char('c') if key_event.modifiers.is_empty() => app.toggle_compare_entry(),
+        KeyCode::Char('a') => app.apply_staged_results(),
+        KeyCode::Char('y') if key_event.modifiers.is_empty() => app.copy_entry_name(false),
+        KeyCode::Char('Y') => app.copy_entry_name(true),
+        KeyCode::Char('C') => app.copy_pane(),
+        KeyCode::Char('r') => app.refresh(),
+        KeyCode::Char('E') => app.export_all(None),
+        KeyCode::Char('x') => app.expand_long_lines = !app.expand_long_lines,
+        KeyCode::Char('w') => app.wrap_enabled = !app.wrap_enabled,
+        KeyCode::Char('l') => app.line_numbers = !app.line_numbers,
+        KeyCode::Char('F') => app.code_pane_hidden = !app.code_pane_hidden,
+        KeyCode::Char('o') if key_event.modifiers.is_empty() => {
+            app.failures_only = !app.failures_only;
+        }
+        KeyCode::Char('v') => {
+            app.show_diagnostics = !app.show_diagnostics;
+            app.results_scroll = 0;
+        }
+        KeyCode::Char('f') => {
+            app.focus = match app.focus {
+                Focus::Entries => Focus::Results,
+                Focus::Results => Focus::Entries,
+            };
+        }
+        KeyCode::Left if key_event.modifiers.contains(KeyModifiers::SHIFT) => {
+            app.results_hscroll = app.results_hscroll.saturating_sub(HSCROLL_COLUMNS);
+        }
+        KeyCode::Right if key_event.modifiers.contains(KeyModifiers::SHIFT) => {
+            app.results_hscroll += HSCROLL_COLUMNS;
+        }
+        KeyCode::Left if app.focus == Focus::Results => {
+            app.results_hscroll = app.results_hscroll.saturating_sub(HSCROLL_COLUMNS);
+        }
+        KeyCode::Right if app.focus == Focus::Results => {
+            app.results_hscroll += HSCROLL_COLUMNS;
+        }
+        KeyCode::Up if app.focus == Focus::Results => {
+            if app.results_cursor == app.results_scroll {
+                app.results_scroll = app.results_scroll.saturating_sub(1);
+            }
+            app.results_cursor = app.results_cursor.saturating_sub(1);
+        }
+        KeyCode::Down if app.focus == Focus::Results => {
+            // Clamped against the content length at draw time.
+            app.results_cursor += 1;
+            if app.results_cursor >= app.results_scroll + app.results_viewport {
+                app.results_scroll += 1;
+            }
+        }
+        KeyCode::Left | KeyCode::Up if app.selected_entry > 0 => {
+            app.push_nav_undo();
+            app.selected_entry -= 1;
+            app.check_and_run_conversion();
+        }
+        KeyCode::Right | KeyCode::Down if app.selected_entry + 1 < app.entry_points.len() => {
+            app.push_nav_undo();
+            app.selected_entry += 1;
+            app.check_and_run_conversion();
+        }
+        KeyCode::Char('z') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+            app.undo_nav();
+        }
+        KeyCode::Char('y') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+            app.redo_nav();
+        }
+        KeyCode::Char(']') => app.switch_file(1),
+        KeyCode::Char('[') => app.switch_file(app.file_position().map_or(0, |(_, n)| n - 1)),
+        KeyCode::Char('q') => return true,
+        KeyCode::Esc if app.search.is_some() => app.search = None,
+        KeyCode::Esc if app.is_reducing() => app.cancel_reduce(),
+        KeyCode::Esc => return true,
+        _ => {}
+    }
+    false
+}
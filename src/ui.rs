@@ -0,0 +1,1356 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Rendering of the TUI frame.
+
+use crate::app::{
+    failures_only, scroll_into_view, App, BuildStatus, Equivalence, Focus, OnBuildStatus,
+    OptimizerDiff,
+};
+use crate::delay::DelaySummary;
+use crate::diff::DiffLine;
+use crate::ir::{ir_op_counts, node_fan, OpStats};
+use crate::search::Search;
+use crate::theme::Theme;
+use crate::watch::WatcherKind;
+use ratatui::{
+    backend::Backend,
+    layout::{Alignment, Constraint, Direction, Layout, Margin, Rect},
+    style::Style,
+    text::{Span, Spans},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Tabs, Wrap},
+    Frame,
+};
+use std::time::Duration;
+
+/// Ops named in the opt IR tab's title; the rest are counted.
+const OP_STATS_SHOWN: usize = 6;
+
+/// How long a toast message replaces the hint bar.
+const TOAST_DURATION: Duration = Duration::from_secs(3);
+
+/// Results lines longer than this many characters are shown with their
+/// middle elided unless long lines are expanded.
+const LONG_LINE_LIMIT: usize = 2000;
+
+/// Characters kept at each end of an elided line.
+const LONG_LINE_KEEP: usize = 200;
+
+/// How long the opt IR pane keeps inline diff markers after a rebuild.
+const INLINE_DIFF_DURATION: Duration = Duration::from_secs(5);
+
+/// Width of the node fan-in/fan-out side panel.
+const NODE_PANEL_WIDTH: u16 = 32;
+
+/// Rows shown in the entry list before it starts scrolling.
+const MAX_VISIBLE_ENTRIES: usize = 8;
+
+/// Rows the results content keeps below the entry list and tabs, borders
+/// included, however short the source.
+const MIN_RESULTS_HEIGHT: u16 = 6;
+
+/// Smallest terminal the layout is drawn in; below it only a resize hint is
+/// shown. The width matches the lowest `--max-width`.
+const MIN_WIDTH: u16 = 40;
+const MIN_HEIGHT: u16 = 14;
+
+/// When a keybinding is relevant enough to show in the hint bar.
+#[derive(Clone, Copy, PartialEq)]
+enum HintContext {
+    Always,
+    DelayTab,
+    VerilogTab,
+    MultipleFiles,
+    EntriesFocused,
+    ResultsFocused,
+    /// Listed in the help overlay but never in the hint bar.
+    Never,
+}
+
+/// A row of the keybinding table that the hint bar is derived from.
+struct KeyBinding {
+    keys: &'static str,
+    action: &'static str,
+    context: HintContext,
+}
+
+const KEY_BINDINGS: &[KeyBinding] = &[
+    KeyBinding {
+        keys: "Tab",
+        action: "next view",
+        context: HintContext::Always,
+    },
+    KeyBinding {
+        keys: "Ctrl+U/O/D/V/P/E",
+        action: "unopt/opt/delay/Verilog/opt diff/equivalence",
+        context: HintContext::Never,
+    },
+    KeyBinding {
+        keys: "↑/↓",
+        action: "entry",
+        context: HintContext::EntriesFocused,
+    },
+    KeyBinding {
+        keys: "(Shift+)←/→",
+        action: "scroll sideways",
+        context: HintContext::ResultsFocused,
+    },
+    KeyBinding {
+        keys: "[/]",
+        action: "prev/next file",
+        context: HintContext::MultipleFiles,
+    },
+    KeyBinding {
+        keys: "f",
+        action: "focus",
+        context: HintContext::Always,
+    },
+    KeyBinding {
+        keys: "F",
+        action: "hide/show source",
+        context: HintContext::Never,
+    },
+    KeyBinding {
+        keys: "w",
+        action: "wrap",
+        context: HintContext::ResultsFocused,
+    },
+    KeyBinding {
+        keys: "PgUp/PgDn",
+        action: "scroll",
+        context: HintContext::Always,
+    },
+    KeyBinding {
+        keys: "j/k",
+        action: "source cursor",
+        context: HintContext::Always,
+    },
+    KeyBinding {
+        keys: "<N> Ctrl+G",
+        action: "go to line",
+        context: HintContext::Never,
+    },
+    KeyBinding {
+        keys: "l",
+        action: "line numbers",
+        context: HintContext::Never,
+    },
+    KeyBinding {
+        keys: "x",
+        action: "expand long lines",
+        context: HintContext::Never,
+    },
+    KeyBinding {
+        keys: "d",
+        action: "delay diff",
+        context: HintContext::DelayTab,
+    },
+    KeyBinding {
+        keys: "D",
+        action: "next delay model",
+        context: HintContext::DelayTab,
+    },
+    KeyBinding {
+        keys: "+/-",
+        action: "pipeline stages",
+        context: HintContext::VerilogTab,
+    },
+    KeyBinding {
+        keys: "r",
+        action: "refresh",
+        context: HintContext::Never,
+    },
+    KeyBinding {
+        keys: "p",
+        action: "pause/resume rebuilds",
+        context: HintContext::Always,
+    },
+    KeyBinding {
+        keys: "m<N>/'<N>",
+        action: "set/jump to bookmark",
+        context: HintContext::Never,
+    },
+    KeyBinding {
+        keys: "a",
+        action: "apply staged build",
+        context: HintContext::Never,
+    },
+    KeyBinding {
+        keys: "e",
+        action: "next stage error",
+        context: HintContext::Never,
+    },
+    KeyBinding {
+        keys: "t",
+        action: "run one test",
+        context: HintContext::Never,
+    },
+    KeyBinding {
+        keys: "Enter",
+        action: "fold/unfold function",
+        context: HintContext::Never,
+    },
+    KeyBinding {
+        keys: "o",
+        action: "failures-only test output",
+        context: HintContext::Never,
+    },
+    KeyBinding {
+        keys: "c",
+        action: "compare entry's opt IR",
+        context: HintContext::Never,
+    },
+    KeyBinding {
+        keys: "i",
+        action: "inline opt IR diffs",
+        context: HintContext::Never,
+    },
+    KeyBinding {
+        keys: "v",
+        action: "stage diagnostics",
+        context: HintContext::Never,
+    },
+    KeyBinding {
+        keys: "y/Y",
+        action: "copy entry name (IR/DSLX)",
+        context: HintContext::Never,
+    },
+    KeyBinding {
+        keys: "C",
+        action: "copy results tab",
+        context: HintContext::Never,
+    },
+    KeyBinding {
+        keys: "/",
+        action: "search",
+        context: HintContext::Always,
+    },
+    KeyBinding {
+        keys: "n/N",
+        action: "next/prev match",
+        context: HintContext::Never,
+    },
+    KeyBinding {
+        keys: ":report",
+        action: "HTML report",
+        context: HintContext::Never,
+    },
+    KeyBinding {
+        keys: ":export csv|json",
+        action: "export delay table",
+        context: HintContext::Never,
+    },
+    KeyBinding {
+        keys: ":export dot",
+        action: "export opt IR graph",
+        context: HintContext::Never,
+    },
+    KeyBinding {
+        keys: "E / :export all DIR",
+        action: "export all artifacts",
+        context: HintContext::Never,
+    },
+    KeyBinding {
+        keys: ":node NAME",
+        action: "node fan-in/out",
+        context: HintContext::Never,
+    },
+    KeyBinding {
+        keys: ":reduce",
+        action: "shrink failing file",
+        context: HintContext::Never,
+    },
+    KeyBinding {
+        keys: ":equiv",
+        action: "check opt/unopt equivalence",
+        context: HintContext::Never,
+    },
+    KeyBinding {
+        keys: "Ctrl+Z/Y",
+        action: "undo/redo navigation",
+        context: HintContext::Never,
+    },
+    KeyBinding {
+        keys: "? / F1",
+        action: "help",
+        context: HintContext::Always,
+    },
+    KeyBinding {
+        keys: "q / Esc",
+        action: "quit",
+        context: HintContext::Always,
+    },
+];
+
+/// Byte range of the middle of `line` to elide, if it is too long to show.
+fn elided_range(line: &str, expand: bool) -> Option<(usize, usize)> {
+    if expand || line.len() <= LONG_LINE_LIMIT {
+        return None;
+    }
+    let chars = line.chars().count();
+    if chars <= LONG_LINE_LIMIT {
+        return None;
+    }
+    let mut offsets = line.char_indices().map(|(i, _)| i);
+    let start = offsets.nth(LONG_LINE_KEEP)?;
+    let end = offsets.nth(chars - 2 * LONG_LINE_KEEP - 1)?;
+    Some((start, end))
+}
+
+/// Lines of a results tab, with overlong lines elided unless `expand`.
+fn results_lines(text: &str, expand: bool, theme: &Theme) -> Vec<Spans<'static>> {
+    text.lines()
+        .map(|line| highlight_line(line, &[], elided_range(line, expand), theme))
+        .collect()
+}
+
+/// The gutter of the 0-based line `index`: its 1-based number, right
+/// aligned, and a space.
+fn line_number(index: usize) -> String {
+    format!("{:>4} ", index + 1)
+}
+
+/// A results pane showing `lines` from the scroll position, numbered when
+/// line numbers are on. When wrapping, the lines above it are dropped
+/// rather than scrolled past, so the scroll position stays a line number
+/// however many rows each line wraps to.
+fn results_paragraph(app: &App, mut lines: Vec<Spans<'static>>) -> Paragraph<'static> {
+    if app.line_numbers {
+        for (i, line) in lines.iter_mut().enumerate() {
+            line.0
+                .insert(0, Span::styled(line_number(i), app.theme.muted));
+        }
+    }
+    if !app.wrap_enabled {
+        let scroll = (app.results_scroll as u16, app.results_hscroll as u16);
+        return Paragraph::new(lines).scroll(scroll);
+    }
+    lines.drain(..app.results_scroll.min(lines.len()));
+    Paragraph::new(lines).wrap(Wrap { trim: false })
+}
+
+/// The cells of each tab title in the bordered tab bar at `area`, laid out
+/// as `Tabs` does: a space either side of each title and a divider between.
+fn tab_title_areas(
+    titles: &[Spans],
+    tab_order: &[(usize, String)],
+    area: Rect,
+) -> Vec<(Rect, usize)> {
+    let mut x = area.x + 1;
+    let right = area.right().saturating_sub(1);
+    let mut areas = Vec::new();
+    for (title, (tab, _)) in titles.iter().zip(tab_order) {
+        if x >= right {
+            break;
+        }
+        let width = (title.width() as u16 + 2).min(right - x);
+        areas.push((
+            Rect {
+                x,
+                y: area.y + 1,
+                width,
+                height: 1,
+            },
+            *tab,
+        ));
+        x += width + 1;
+    }
+    areas
+}
+
+/// Builds the bottom hint bar from `KEY_BINDINGS`, keeping only the bindings
+/// relevant to the current view.
+fn hint_bar_spans(app: &App) -> Spans<'static> {
+    if let Some(input) = &app.command_input {
+        return Spans::from(format!(":{}", input));
+    }
+    if let Some(search) = &app.search {
+        return search_bar_spans(search, &app.theme);
+    }
+    if let Some((message, shown_at)) = &app.toast {
+        if shown_at.elapsed() < TOAST_DURATION {
+            return Spans::from(Span::styled(message.clone(), app.theme.badge));
+        }
+    }
+    let mut spans = Vec::new();
+    for binding in KEY_BINDINGS {
+        let relevant = match binding.context {
+            HintContext::Always => true,
+            HintContext::DelayTab => app.selected_tab == 2,
+            HintContext::VerilogTab => app.selected_tab == 7,
+            HintContext::MultipleFiles => app.file_position().is_some(),
+            HintContext::EntriesFocused => app.focus == Focus::Entries,
+            HintContext::ResultsFocused => app.focus == Focus::Results,
+            HintContext::Never => false,
+        };
+        if !relevant {
+            continue;
+        }
+        spans.push(Span::styled(binding.keys, app.theme.key_hint));
+        spans.push(Span::raw(format!(" {}  ", binding.action)));
+    }
+    Spans::from(spans)
+}
+
+/// The `/` prompt: query, match position and the active options.
+fn search_bar_spans(search: &Search, theme: &Theme) -> Spans<'static> {
+    let option = |label: &'static str, on: bool| {
+        Span::styled(label, if on { theme.badge } else { theme.muted })
+    };
+    let mut spans = vec![
+        Span::raw(format!("/{}", search.query)),
+        Span::styled(format!("  {}  ", search.position()), theme.tab_title),
+        option("[Aa]", search.case_sensitive),
+        Span::raw(" "),
+        option("[word]", search.whole_word),
+        Span::raw(" "),
+        option("[regex]", search.is_regex()),
+        Span::raw("  "),
+    ];
+    if search.invalid_regex {
+        spans.push(Span::styled("invalid regex  ", theme.error));
+    }
+    let hints: &[(&'static str, &'static str)] = if search.editing {
+        &[
+            ("Alt+C", "case"),
+            ("Alt+W", "whole word"),
+            ("re:", "regex"),
+            ("Enter", "done"),
+        ]
+    } else {
+        &[("n/N", "next/prev"), ("Esc", "clear")]
+    };
+    for (keys, action) in hints {
+        spans.push(Span::styled(*keys, theme.key_hint));
+        spans.push(Span::raw(format!(" {}  ", action)));
+    }
+    Spans::from(spans)
+}
+
+/// The status banner for how far the last build got. A failure names the
+/// first stage in the error pane; a build without an interpreter is not one.
+fn build_status_banner(app: &App, status: BuildStatus) -> (String, Style) {
+    let failed_stage = app
+        .stage_errors
+        .first()
+        .map_or("a later stage", |(stage, _)| stage.as_str());
+    let theme = &app.theme;
+    match status {
+        BuildStatus::NotRun => (String::from("Build did not run"), theme.status_err),
+        BuildStatus::ConversionFailed => (String::from("IR conversion failed"), theme.status_err),
+        BuildStatus::ConversionOk => (
+            format!("IR converted; {} failed", failed_stage),
+            theme.status_err,
+        ),
+        BuildStatus::OptOk => (
+            format!("IR optimized; {} failed", failed_stage),
+            theme.status_err,
+        ),
+        BuildStatus::TestsPassed => (String::from("Tests passed"), theme.status_ok),
+        BuildStatus::TestsFailed => (String::from("Tests failed"), theme.status_err),
+        BuildStatus::TestsSkipped => (
+            String::from("Built; tests skipped (no interpreter)"),
+            theme.key_hint,
+        ),
+    }
+}
+
+/// The delay tab's header, e.g. `critical path: 342ps · add.3 120ps, ...`.
+fn delay_summary_spans(summary: &DelaySummary, theme: &Theme) -> Spans<'static> {
+    let mut spans = vec![Span::styled(
+        format!("critical path: {}ps", summary.critical_path_ps),
+        theme.tab_title,
+    )];
+    if !summary.top_nodes.is_empty() {
+        let nodes: Vec<String> = summary
+            .top_nodes
+            .iter()
+            .map(|entry| format!("{} {}ps", entry.node, entry.delay_ps))
+            .collect();
+        spans.push(Span::styled(
+            format!(" · slowest: {}", nodes.join(", ")),
+            theme.muted,
+        ));
+    }
+    Spans::from(spans)
+}
+
+/// The opt IR tab's title: node count and the most frequent ops.
+fn op_stats_spans(stats: &OpStats, theme: &Theme) -> Spans<'static> {
+    let mut spans = vec![Span::styled(
+        format!("{} nodes", stats.nodes),
+        theme.tab_title,
+    )];
+    if !stats.ops.is_empty() {
+        let mut ops: Vec<String> = stats
+            .ops
+            .iter()
+            .take(OP_STATS_SHOWN)
+            .map(|(op, count)| format!("{} {}", op, count))
+            .collect();
+        if stats.ops.len() > OP_STATS_SHOWN {
+            ops.push(format!("+{} more", stats.ops.len() - OP_STATS_SHOWN));
+        }
+        spans.push(Span::styled(format!(" · {}", ops.join(", ")), theme.muted));
+    }
+    Spans::from(spans)
+}
+
+/// Border style marking `pane` as the target of the arrow keys.
+fn focus_border(app: &App, pane: Focus) -> Style {
+    if app.focus == pane {
+        app.theme.selected
+    } else {
+        Style::default()
+    }
+}
+
+/// `line` with the given byte ranges styled as search matches. The
+/// `omitted` byte range is replaced by a marker, styled as a match when a
+/// match falls inside it.
+fn highlight_line(
+    line: &str,
+    ranges: &[(usize, usize, bool)],
+    omitted: Option<(usize, usize)>,
+    theme: &Theme,
+) -> Spans<'static> {
+    let match_style = |current| {
+        if current {
+            theme.search_current
+        } else {
+            theme.search_match
+        }
+    };
+    // Pushes `line[from..to]`, with the parts of `ranges` inside it styled.
+    let push_segment = |spans: &mut Vec<Span<'static>>, from: usize, to: usize| {
+        let mut last = from;
+        for &(start, end, current) in ranges {
+            let (start, end) = (start.max(from), end.min(to));
+            if start >= end {
+                continue;
+            }
+            spans.push(Span::raw(line[last..start].to_string()));
+            spans.push(Span::styled(
+                line[start..end].to_string(),
+                match_style(current),
+            ));
+            last = end;
+        }
+        spans.push(Span::raw(line[last..to].to_string()));
+    };
+    let mut spans = Vec::new();
+    match omitted {
+        None => push_segment(&mut spans, 0, line.len()),
+        Some((start, end)) => {
+            push_segment(&mut spans, 0, start);
+            let hidden: Vec<bool> = ranges
+                .iter()
+                .filter(|r| r.0 < end && r.1 > start)
+                .map(|r| r.2)
+                .collect();
+            let style = if hidden.is_empty() {
+                theme.muted
+            } else {
+                match_style(hidden.contains(&true))
+            };
+            spans.push(Span::styled(
+                format!(
+                    " … ({} chars omitted, x to expand) … ",
+                    line[start..end].chars().count()
+                ),
+                style,
+            ));
+            push_segment(&mut spans, end, line.len());
+        }
+    }
+    Spans::from(spans)
+}
+
+/// Side panel listing the operands and users of the node named `name`.
+fn render_node_panel<B: Backend>(
+    f: &mut Frame<B>,
+    area: Rect,
+    ir: &str,
+    name: &str,
+    theme: &Theme,
+) {
+    let lines: Vec<Spans> = match node_fan(ir, name) {
+        Some(fan) => {
+            let mut lines = vec![
+                Spans::from(Span::styled(
+                    format!("{} = {}(..)", fan.node.name, fan.node.op),
+                    theme.tab_title,
+                )),
+                Spans::from(Span::styled(format!("in {}", fan.function), theme.muted)),
+                Spans::from(""),
+                Spans::from(Span::styled("fan-in", theme.badge)),
+            ];
+            let none = || Spans::from(Span::styled("  —", theme.muted));
+            if fan.node.operands.is_empty() {
+                lines.push(none());
+            }
+            lines.extend(
+                fan.node
+                    .operands
+                    .iter()
+                    .map(|o| Spans::from(format!("  {}", o))),
+            );
+            lines.push(Spans::from(""));
+            lines.push(Spans::from(Span::styled("fan-out", theme.badge)));
+            if fan.users.is_empty() {
+                lines.push(none());
+            }
+            lines.extend(fan.users.iter().map(|u| Spans::from(format!("  {}", u))));
+            lines
+        }
+        None => vec![Spans::from(Span::styled(
+            format!("no node named {}", name),
+            theme.error,
+        ))],
+    };
+    let panel = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(":node to close"),
+    );
+    f.render_widget(panel, area);
+}
+
+/// Draws a one-column scrollbar gutter: a thumb sized and positioned by the
+/// visible window over a track spanning the pane's inner height.
+fn render_scrollbar<B: Backend>(
+    f: &mut Frame<B>,
+    area: Rect,
+    offset: usize,
+    total: usize,
+    viewport: usize,
+    theme: &Theme,
+) {
+    // Line the track up with the bordered pane's inner rows.
+    let track_height = area.height.saturating_sub(2) as usize;
+    if track_height == 0 {
+        return;
+    }
+    let (thumb_start, thumb_len) = if total <= viewport || total == 0 {
+        (0, track_height)
+    } else {
+        let len = (track_height * viewport / total).clamp(1, track_height);
+        let start = (track_height - len) * offset / (total - viewport);
+        (start, len)
+    };
+    let rows: Vec<Spans> = (0..track_height)
+        .map(|row| {
+            if row >= thumb_start && row < thumb_start + thumb_len {
+                Spans::from(Span::styled("█", theme.scrollbar_thumb))
+            } else {
+                Spans::from(Span::styled("│", theme.scrollbar_track))
+            }
+        })
+        .collect();
+    let track_area = Rect {
+        y: area.y + 1,
+        height: track_height as u16,
+        ..area
+    };
+    f.render_widget(Paragraph::new(rows), track_area);
+}
+
+/// Caps `area` at `max_width` columns, centered horizontally.
+fn centered_area(area: Rect, max_width: Option<u16>) -> Rect {
+    match max_width {
+        Some(max_width) if area.width > max_width => Rect {
+            x: area.x + (area.width - max_width) / 2,
+            width: max_width,
+            ..area
+        },
+        _ => area,
+    }
+}
+
+/// The `?` overlay: every row of `KEY_BINDINGS`, centered over the layout,
+/// scrolling with j/k and PgUp/PgDn when taller than the terminal.
+fn render_help<B: Backend>(f: &mut Frame<B>, area: Rect, app: &mut App) {
+    let theme = &app.theme;
+    let keys_width = KEY_BINDINGS
+        .iter()
+        .map(|binding| binding.keys.chars().count())
+        .max()
+        .unwrap_or(0);
+    let lines: Vec<Spans> = KEY_BINDINGS
+        .iter()
+        .map(|binding| {
+            Spans::from(vec![
+                Span::styled(
+                    format!("{:<width$}", binding.keys, width = keys_width),
+                    theme.key_hint,
+                ),
+                Span::raw(format!("  {}", binding.action)),
+            ])
+        })
+        .collect();
+    let widest = lines.iter().map(Spans::width).max().unwrap_or(0) as u16;
+    let width = (widest + 2).min(area.width);
+    let height = (lines.len() as u16 + 2).min(area.height);
+    let help_area = Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    };
+    let viewport = height.saturating_sub(2) as usize;
+    let scroll = app.help_scroll.min(lines.len().saturating_sub(viewport));
+    let mut title = vec![Span::styled("keys (? to close)", theme.badge)];
+    if lines.len() > viewport {
+        let more = match (scroll > 0, scroll + viewport < lines.len()) {
+            (true, true) => "↑↓",
+            (true, false) => "↑",
+            _ => "↓",
+        };
+        title.push(Span::styled(
+            format!(
+                " {} more · {}–{} of {} · j/k PgUp/PgDn scroll",
+                more,
+                scroll + 1,
+                scroll + viewport,
+                lines.len()
+            ),
+            theme.muted,
+        ));
+    }
+    let help = Paragraph::new(lines).scroll((scroll as u16, 0)).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(Spans::from(title)),
+    );
+    app.help_scroll = scroll;
+    app.help_viewport = viewport;
+    f.render_widget(Clear, help_area);
+    f.render_widget(help, help_area);
+}
+
+/// Replaces the whole layout with a request to enlarge the terminal, which
+/// is drawn again normally on the first frame after a big enough resize.
+fn draw_too_small<B: Backend>(f: &mut Frame<B>, area: Rect, theme: &Theme) {
+    let message = format!(
+        "terminal too small — resize to at least {}×{} (now {}×{})",
+        MIN_WIDTH, MIN_HEIGHT, area.width, area.height
+    );
+    let lines = (message.chars().count() as u16).div_ceil(area.width.max(1));
+    let message_area = Rect {
+        y: area.y + area.height.saturating_sub(lines) / 2,
+        height: lines.min(area.height),
+        ..area
+    };
+    let paragraph = Paragraph::new(Span::styled(message, theme.error))
+        .alignment(Alignment::Center)
+        .wrap(Wrap { trim: true });
+    f.render_widget(paragraph, message_area);
+}
+
+/// Formats a byte count as e.g. "812 B", "147 KB" or "2.3 MB".
+fn human_size(bytes: usize) -> String {
+    const KB: usize = 1024;
+    const MB: usize = 1024 * KB;
+    if bytes < KB {
+        format!("{} B", bytes)
+    } else if bytes < MB {
+        format!("{} KB", bytes / KB)
+    } else {
+        format!("{:.1} MB", bytes as f64 / MB as f64)
+    }
+}
+
+/// Renders the whole frame. Takes `app` mutably only to clamp the results
+/// scroll offset to the current content length.
+pub(crate) fn draw<B: Backend>(f: &mut Frame<B>, app: &mut App) {
+    let size = centered_area(f.size(), app.max_width);
+    app.reset_replaced_scrolls();
+    if size.width < MIN_WIDTH || size.height < MIN_HEIGHT {
+        draw_too_small(f, size, &app.theme);
+        return;
+    }
+    let code_line_count = app.code.lines().count() as u16;
+    // Show up to MAX_VISIBLE_ENTRIES rows; the list scrolls beyond that.
+    let entry_list_height = (app.entry_points.len().clamp(1, MAX_VISIBLE_ENTRIES) + 2) as u16;
+    // Compute top height: content lines + 6, but at least 10 and enough for
+    // the entry list, tabs and some results, while leaving at least 3 lines
+    // for error pane plus 1 for the hint bar
+    let mut top_height = std::cmp::min(
+        (code_line_count + 6)
+            .max(10)
+            .max(entry_list_height + 3 + MIN_RESULTS_HEIGHT),
+        size.height.saturating_sub(4),
+    );
+    if app.code_pane_hidden {
+        // Nothing to size to the source; the results get two thirds.
+        top_height = top_height.max(size.height * 2 / 3);
+    }
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Length(top_height),
+                Constraint::Min(3),
+                Constraint::Length(1),
+            ]
+            .as_ref(),
+        )
+        .split(size);
+
+    // The source pane and the status line below it, and the results column.
+    // With the source pane hidden the results take the full width and the
+    // status line moves below them.
+    let (source_area, status_area, results_area) = if app.code_pane_hidden {
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(1)].as_ref())
+            .split(chunks[0]);
+        (
+            Rect {
+                height: 0,
+                ..rows[0]
+            },
+            rows[1],
+            rows[0],
+        )
+    } else {
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+            .split(chunks[0]);
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(1)].as_ref())
+            .split(columns[0]);
+        (rows[0], rows[1], columns[1])
+    };
+
+    let code_inner_width = source_area.width.saturating_sub(2) as usize;
+    let code_inner_height = source_area.height.saturating_sub(2) as usize;
+    let code_with_line_numbers: Vec<Spans> = app
+        .code
+        .lines()
+        .enumerate()
+        .map(|(i, line)| {
+            let text = format!("{}{}", line_number(i), line);
+            // The error line keeps its highlight when the cursor is on it.
+            if app.error_span.is_some_and(|(line, _)| line == i + 1) {
+                Spans::from(Span::styled(
+                    format!("{:<width$}", text, width = code_inner_width),
+                    app.theme.error_line,
+                ))
+            } else if i == app.source_cursor_row {
+                Spans::from(Span::styled(
+                    format!("{:<width$}", text, width = code_inner_width),
+                    app.theme.cursor_line,
+                ))
+            } else {
+                Spans::from(text)
+            }
+        })
+        .collect();
+    app.source_scroll = scroll_into_view(
+        app.source_scroll,
+        app.source_cursor_row,
+        code_inner_height,
+        app.scroll_off,
+    )
+    .min(
+        code_with_line_numbers
+            .len()
+            .saturating_sub(code_inner_height),
+    );
+    let code_scroll = app.source_scroll as u16;
+    let mut title = vec![Span::raw(
+        app.updated_label().unwrap_or_else(|| String::from("File")),
+    )];
+    title.push(Span::raw(format!(
+        " {} ({})",
+        app.file_path.as_deref().unwrap_or(""),
+        human_size(app.code.len())
+    )));
+    if let Some((position, count)) = app.file_position() {
+        title.push(Span::styled(
+            format!(" [file {}/{}]", position, count),
+            app.theme.muted,
+        ));
+    }
+    if app.watcher_kind == WatcherKind::Polling {
+        title.push(Span::styled(" [polling]", app.theme.muted));
+    }
+    if app.paused {
+        title.push(Span::styled(
+            format!(" [paused — {} changes pending]", app.pending_changes),
+            app.theme.badge,
+        ));
+    }
+    if app.has_staged_results() {
+        title.push(Span::styled(" [new results — a to apply]", app.theme.badge));
+    } else if app.manual_apply {
+        title.push(Span::styled(" [manual apply]", app.theme.muted));
+    }
+    match app.on_build_status {
+        Some(OnBuildStatus::Running) => {
+            title.push(Span::styled(" [on-build: running]", app.theme.muted));
+        }
+        Some(OnBuildStatus::Exited(Some(0))) => {
+            title.push(Span::styled(" [on-build: ok]", app.theme.success));
+        }
+        Some(OnBuildStatus::Exited(code)) => {
+            let status = code.map_or_else(|| String::from("failed"), |c| format!("exit {}", c));
+            title.push(Span::styled(
+                format!(" [on-build: {}]", status),
+                app.theme.warning,
+            ));
+        }
+        None => {}
+    }
+    for warning in app.input_warning.iter().chain(&app.watch_warning) {
+        title.push(Span::styled(
+            format!(" [warning: {}]", warning),
+            app.theme.warning,
+        ));
+    }
+    // With the source pane hidden its title, and the file and build state it
+    // shows, moves to the results block.
+    let results_title = if app.code_pane_hidden {
+        let mut results_title = vec![Span::raw("Results — ")];
+        results_title.append(&mut title);
+        Spans::from(results_title)
+    } else {
+        let code_widget = Paragraph::new(code_with_line_numbers)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(Spans::from(title)),
+            )
+            .scroll((code_scroll, 0));
+        f.render_widget(code_widget, source_area);
+        Spans::from("Results")
+    };
+
+    let banner = if app.file_empty {
+        Some((
+            String::from("File is empty — waiting for content"),
+            app.theme.key_hint,
+        ))
+    } else if app.no_tests_ran() {
+        Some((String::from("No tests"), app.theme.key_hint))
+    } else {
+        app.build_status
+            .map(|status| build_status_banner(app, status))
+    };
+    let banner_width = banner
+        .as_ref()
+        .map_or(0, |(text, _)| text.chars().count() + 1);
+    if let Some((text, style)) = banner {
+        f.render_widget(Paragraph::new(text).style(style), status_area);
+    }
+    let mut status_right = Vec::new();
+    if app.paused {
+        status_right.push(Span::styled(" PAUSED ", app.theme.badge));
+    }
+    if app.wrap_enabled {
+        status_right.push(Span::styled(" wrap ", app.theme.muted));
+    }
+    if !app.warnings.is_empty() {
+        status_right.push(Span::styled(
+            format!(
+                " ⚠ {} warning{} ",
+                app.warnings.len(),
+                if app.warnings.len() == 1 { "" } else { "s" }
+            ),
+            app.theme.warning,
+        ));
+    }
+    if let Some((passed, ran)) = app.test_badge_counts() {
+        let (mark, style) = if passed == ran {
+            ("✓", app.theme.success)
+        } else {
+            ("✗", app.theme.error)
+        };
+        status_right.push(Span::styled(
+            format!(" tests {}/{} {} ", passed, ran, mark),
+            style,
+        ));
+    }
+    if let Some(schedule) = &app.pipeline_schedule {
+        status_right.push(Span::styled(
+            format!(
+                " {} stage{} @ {}ps ",
+                schedule.stages.len(),
+                if schedule.stages.len() == 1 { "" } else { "s" },
+                schedule.clock_period_ps
+            ),
+            app.theme.muted,
+        ));
+    }
+    if let Some(timing) = app.timing_label() {
+        status_right.push(Span::styled(format!(" {} ", timing), app.theme.muted));
+    }
+    if app.code.len() > app.large_file_warn_bytes {
+        status_right.push(Span::styled(" ⚠ large file ", app.theme.warning));
+    }
+    // Last, so they are the first to be cut when the line is full.
+    if let Some(timings) = app.stage_timings_label() {
+        status_right.push(Span::styled(format!(" {} ", timings), app.theme.muted));
+    }
+    if !status_right.is_empty() {
+        // The banner keeps its room; what does not fit is cut on the right.
+        let width: usize = status_right
+            .iter()
+            .map(|span| span.content.chars().count())
+            .sum::<usize>()
+            .min((status_area.width as usize).saturating_sub(banner_width));
+        let status_chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Min(0), Constraint::Length(width as u16)].as_ref())
+            .split(status_area);
+        f.render_widget(Paragraph::new(Spans::from(status_right)), status_chunks[1]);
+    }
+
+    let results_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Length(entry_list_height),
+                Constraint::Length(3),
+                Constraint::Min(0),
+            ]
+            .as_ref(),
+        )
+        .split(results_area);
+
+    let tabs_titles: Vec<Spans> = app
+        .tab_order
+        .iter()
+        .map(|(tab, label)| {
+            let mut title = vec![Span::styled(label.clone(), app.theme.tab_title)];
+            if *tab == 2 {
+                title.push(Span::styled(
+                    format!(" ({})", app.delay_model),
+                    app.theme.muted,
+                ));
+            }
+            if *tab == 2 && app.show_delay_diff {
+                title.push(Span::styled(" Δ diff", app.theme.badge));
+            }
+            Spans::from(title)
+        })
+        .collect();
+    let selected_tab_position = app
+        .tab_order
+        .iter()
+        .position(|(tab, _)| *tab == app.selected_tab)
+        .unwrap_or(0);
+    let op_counts = ir_op_counts(&app.unopt_ir);
+    let show_signatures = results_chunks[0].width > 60;
+    let entry_items: Vec<ListItem> = app
+        .entry_points
+        .iter()
+        .enumerate()
+        .map(|(i, ep)| {
+            let marker = match app.entry_status.get(ep) {
+                Some(true) => Span::styled("✓ ", app.theme.success),
+                Some(false) => Span::styled("✗ ", app.theme.error),
+                None => Span::styled("· ", app.theme.muted),
+            };
+            let mut spans = vec![marker, Span::raw(ep.clone())];
+            let signature = app.entry_signatures.get(i).map_or("", String::as_str);
+            if show_signatures {
+                if let Some((params, ret)) = signature.rsplit_once(" -> ") {
+                    spans.push(Span::raw(format!("{} -> ", params)));
+                    spans.push(Span::styled(ret.to_string(), app.theme.tab_title));
+                }
+            }
+            if let Some(count) = op_counts.get(ep) {
+                spans.push(Span::styled(format!("  ({} ops)", count), app.theme.muted));
+            }
+            if app.compare_entry.as_ref() == Some(ep) {
+                spans.push(Span::styled("  [compare]", app.theme.badge));
+            }
+            ListItem::new(Spans::from(spans))
+        })
+        .collect();
+    let entry_widget = List::new(entry_items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(focus_border(app, Focus::Entries))
+                .title("Entry (use ↑/↓ to change)"),
+        )
+        .highlight_style(app.theme.selected)
+        .highlight_symbol("> ");
+    let mut entry_state = ListState::default();
+    entry_state.select(Some(app.selected_entry));
+    f.render_stateful_widget(entry_widget, results_chunks[0], &mut entry_state);
+    // The list scrolls just far enough to show the selected entry.
+    let entry_rows = results_chunks[0].inner(&Margin {
+        vertical: 1,
+        horizontal: 1,
+    });
+    app.mouse_areas.entries = entry_rows;
+    app.mouse_areas.first_entry = app
+        .selected_entry
+        .saturating_sub((entry_rows.height as usize).saturating_sub(1));
+    app.mouse_areas.tabs = tab_title_areas(&tabs_titles, &app.tab_order, results_chunks[1]);
+
+    let tabs = Tabs::new(tabs_titles)
+        .select(selected_tab_position)
+        .block(Block::default().borders(Borders::ALL).title(results_title))
+        .highlight_style(app.theme.selected);
+    f.render_widget(tabs, results_chunks[1]);
+
+    let inline_diff = app.selected_tab == 1
+        && !app.show_diagnostics
+        && app
+            .opt_ir_changed_at
+            .is_some_and(|changed_at| changed_at.elapsed() < INLINE_DIFF_DURATION);
+    let delay_diff = app.selected_tab == 2 && app.show_delay_diff && !app.show_diagnostics;
+    let optimizer_diff = app.selected_tab == 8 && !app.show_diagnostics;
+    let diff_view = inline_diff || delay_diff || optimizer_diff;
+    let mut content: Vec<Spans> = if optimizer_diff {
+        match &app.optimizer_diff {
+            OptimizerDiff::Lines(lines) => {
+                lines.iter().map(|line| line.to_spans(&app.theme)).collect()
+            }
+            other => vec![Spans::from(Span::styled(
+                other.placeholder().unwrap_or_default(),
+                app.theme.placeholder,
+            ))],
+        }
+    } else if inline_diff {
+        app.opt_ir_diff.to_spans(&app.theme)
+    } else if delay_diff {
+        app.delay_diff.to_spans(&app.theme)
+    } else {
+        results_lines(&app.tab_text(), app.expand_long_lines, &app.theme)
+    };
+    if let (9, false, Equivalence::Checked { equivalent, .. }) =
+        (app.selected_tab, app.show_diagnostics, &app.equivalence)
+    {
+        // The verdict line, colored like the test status.
+        let style = if *equivalent {
+            app.theme.success
+        } else {
+            app.theme.error
+        };
+        if let Some(verdict) = content.first_mut() {
+            for span in verdict.0.iter_mut() {
+                span.style = span.style.patch(style);
+            }
+        }
+    }
+    let content_area = match &app.inspected_node {
+        Some(name) => {
+            let inspect_chunks = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Min(0), Constraint::Length(NODE_PANEL_WIDTH)].as_ref())
+                .split(results_chunks[2]);
+            // Fan-in/out of the IR being looked at; the opt IR otherwise.
+            let ir = if app.selected_tab == 0 {
+                &app.unopt_ir
+            } else {
+                &app.opt_ir
+            };
+            render_node_panel(f, inspect_chunks[1], ir, name, &app.theme);
+            inspect_chunks[0]
+        }
+        None => results_chunks[2],
+    };
+    let content_chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Min(0), Constraint::Length(1)].as_ref())
+        .split(content_area);
+    let compare = match &app.compare_entry {
+        Some(entry) if app.selected_tab == 1 && !app.show_diagnostics => Some(entry.clone()),
+        _ => None,
+    };
+    let (main_area, compare_area) = if compare.is_some() {
+        let halves = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+            .split(content_chunks[0]);
+        (halves[0], Some(halves[1]))
+    } else {
+        (content_chunks[0], None)
+    };
+    let content_line_count = match compare {
+        Some(_) => content.len().max(app.compare_opt_ir.lines().count()),
+        None => content.len(),
+    };
+    let content_viewport = main_area.height.saturating_sub(2) as usize;
+    app.results_scroll = app
+        .results_scroll
+        .min(content_line_count.saturating_sub(content_viewport));
+    app.results_viewport = content_viewport;
+    // Sideways scrolling stops once the longest line's end is in view.
+    let widest_line = content.iter().map(Spans::width).max().unwrap_or(0);
+    app.results_hscroll = app
+        .results_hscroll
+        .min(widest_line.saturating_sub(main_area.width.saturating_sub(2) as usize));
+    app.results_cursor = app
+        .results_cursor
+        .max(app.results_scroll)
+        .min(app.results_scroll + content_viewport.saturating_sub(1))
+        .min(content_line_count.saturating_sub(1));
+    app.update_search();
+    if let (Some(search), false) = (&app.search, diff_view) {
+        // Only the visible lines are re-split into highlighted spans.
+        let text = app.tab_text();
+        let visible = app.results_scroll..app.results_scroll + content_viewport;
+        for (i, line) in text
+            .lines()
+            .enumerate()
+            .skip(visible.start)
+            .take(visible.len())
+        {
+            // Matches are in line order, so each line's are a contiguous run.
+            let first = search.matches.partition_point(|m| m.0 < i);
+            let ranges: Vec<(usize, usize, bool)> = search.matches[first..]
+                .iter()
+                .take_while(|m| m.0 == i)
+                .enumerate()
+                .map(|(k, m)| (m.1, m.2, first + k == search.current))
+                .collect();
+            if !ranges.is_empty() {
+                let omitted = elided_range(line, app.expand_long_lines);
+                content[i] = highlight_line(line, &ranges, omitted, &app.theme);
+            }
+        }
+    }
+    if app.focus == Focus::Results {
+        if let Some(line) = content.get_mut(app.results_cursor) {
+            for span in line.0.iter_mut() {
+                span.style = span.style.patch(app.theme.cursor_line);
+            }
+        }
+    }
+    let mut content_block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(focus_border(app, Focus::Results));
+    if app.show_diagnostics {
+        content_block =
+            content_block.title(Span::styled("diagnostics (v to close)", app.theme.badge));
+    } else if let Some(summary) = app.delay_summary.as_ref().filter(|_| app.selected_tab == 2) {
+        content_block = content_block.title(delay_summary_spans(summary, &app.theme));
+    } else if let Some(stats) = app.opt_stats.as_ref().filter(|_| app.selected_tab == 1) {
+        content_block = content_block.title(op_stats_spans(stats, &app.theme));
+    } else if let (true, OptimizerDiff::Lines(lines)) = (optimizer_diff, &app.optimizer_diff) {
+        let removed = lines
+            .iter()
+            .filter(|line| matches!(line, DiffLine::Removed(_)))
+            .count();
+        let added = lines
+            .iter()
+            .filter(|line| matches!(line, DiffLine::Added(_)))
+            .count();
+        content_block = content_block.title(Span::styled(
+            if removed + added == 0 {
+                String::from("unopt → opt: no changes")
+            } else {
+                format!("unopt → opt: −{} +{} lines", removed, added)
+            },
+            app.theme.muted,
+        ));
+    }
+    if let (Some(entry), Some(area)) = (compare, compare_area) {
+        if let Some(selected) = app.entry_points.get(app.selected_entry) {
+            content_block = content_block.title(selected.as_str());
+        }
+        let compare_lines = results_lines(&app.compare_opt_ir, app.expand_long_lines, &app.theme);
+        let compare_widget = results_paragraph(app, compare_lines).block(
+            Block::default().borders(Borders::ALL).title(Span::styled(
+                format!("compare: {} (c to close)", entry),
+                app.theme.badge,
+            )),
+        );
+        f.render_widget(compare_widget, area);
+    }
+    let content_widget = results_paragraph(app, content).block(content_block);
+    f.render_widget(content_widget, main_area);
+    app.mouse_areas.results = content_chunks[0];
+    render_scrollbar(
+        f,
+        content_chunks[1],
+        app.results_scroll,
+        content_line_count,
+        content_viewport,
+        &app.theme,
+    );
+
+    // Error pane always shown at the bottom
+    let error_widget = if app.build_succeeded() {
+        let mut lines: Vec<Spans> = app
+            .warnings
+            .iter()
+            .map(|w| Spans::from(Span::styled(format!("warning: {}", w), app.theme.warning)))
+            .collect();
+        let mut test_output = app
+            .test_output
+            .clone()
+            .unwrap_or_else(|| match app.build_status {
+                Some(BuildStatus::TestsSkipped) => String::from(
+                    "[ tests skipped: dslx_interpreter_main not found in XLSYNTH_TOOLS ]",
+                ),
+                _ => String::from("[ no test output ]"),
+            });
+        if app.failures_only {
+            test_output = failures_only(&test_output);
+        }
+        lines.extend(test_output.lines().map(|l| Spans::from(l.to_string())));
+        let mode = if app.jit_compare {
+            "interpreter + JIT compare"
+        } else {
+            "interpreter only"
+        };
+        let mut title = match &app.test_filter {
+            Some(test) => format!("test output ({}, only {}", mode, test),
+            None => format!("test output ({}", mode),
+        };
+        if app.failures_only {
+            title.push_str(", failures only");
+        }
+        title.push(')');
+        Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(title))
+    } else if let Some((stage, error)) = app.stage_errors.get(app.selected_error) {
+        let mut title = vec![Span::styled(
+            format!(
+                "Error {}/{}: {}",
+                app.selected_error + 1,
+                app.stage_errors.len(),
+                stage
+            ),
+            app.theme.error,
+        )];
+        if app.stage_errors.len() > 1 {
+            title.push(Span::styled(" (e: next)", app.theme.muted));
+        }
+        let error = if app.failures_only && stage == "dslx_interpreter_main" {
+            title.push(Span::styled(" (failures only)", app.theme.muted));
+            failures_only(error)
+        } else {
+            error.clone()
+        };
+        Paragraph::new(error).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(Spans::from(title)),
+        )
+    } else if let Some(error) = &app.error_message {
+        Paragraph::new(error.clone()).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(Spans::from(Span::styled("Error", app.theme.error))),
+        )
+    } else {
+        Paragraph::new("[ none ]")
+            .style(app.theme.placeholder)
+            .block(Block::default().borders(Borders::ALL).title("Error"))
+    };
+    f.render_widget(error_widget, chunks[1]);
+
+    f.render_widget(Paragraph::new(hint_bar_spans(app)), chunks[2]);
+    if app.show_help {
+        render_help(f, size, app);
+    }
+}
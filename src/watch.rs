@@ -0,0 +1,173 @@
+// SPDX-License-Identifier: Apache-2.0
+
+//! Detection of changes to the watched files and the modules they import.
+
+use notify::{Config, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::time::{Duration, Instant, SystemTime};
+
+/// How long a change to the file may go without a watch event before native
+/// watching is presumed dead.
+const STALE_WATCH_AFTER: Duration = Duration::from_secs(3);
+
+/// How file changes are being detected.
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum WatcherKind {
+    Notify,
+    Polling,
+}
+
+pub(crate) fn start_notify_watcher(
+    file_path: &str,
+    tx: Sender<notify::Result<notify::Event>>,
+) -> notify::Result<RecommendedWatcher> {
+    let mut watcher: RecommendedWatcher = Watcher::new(tx, Config::default())?;
+    watcher.watch(Path::new(file_path), RecursiveMode::NonRecursive)?;
+    Ok(watcher)
+}
+
+/// Fallback for environments without inotify (e.g. some containers): checks
+/// the file's mtime every `interval` and sends a synthetic `Modify` event
+/// whenever it changes.
+pub(crate) fn start_polling_watcher(
+    path: PathBuf,
+    interval: Duration,
+    tx: Sender<notify::Result<notify::Event>>,
+) {
+    std::thread::spawn(move || {
+        let mut last = mtime(&path);
+        loop {
+            std::thread::sleep(interval);
+            let current = mtime(&path);
+            if current != last {
+                last = current;
+                let event = notify::Event::new(EventKind::Modify(notify::event::ModifyKind::Any))
+                    .add_path(path.clone());
+                if tx.send(Ok(event)).is_err() {
+                    return;
+                }
+            }
+        }
+    });
+}
+
+fn mtime(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Notices when the file's mtime changes but native watching reports
+/// nothing, as happens on some network mounts or when an editor replaces the
+/// file and the watch stays on the old one.
+pub(crate) struct StaleWatchCheck {
+    path: PathBuf,
+    mtime: Option<SystemTime>,
+    last_check: Instant,
+    unreported_since: Option<Instant>,
+}
+
+impl StaleWatchCheck {
+    pub(crate) fn new(path: PathBuf) -> Self {
+        Self {
+            mtime: mtime(&path),
+            path,
+            last_check: Instant::now(),
+            unreported_since: None,
+        }
+    }
+
+    /// Records that a watch event arrived, accounting for the current mtime.
+    pub(crate) fn saw_event(&mut self) {
+        self.mtime = mtime(&self.path);
+        self.unreported_since = None;
+    }
+
+    /// Whether the file has changed without any event for
+    /// `STALE_WATCH_AFTER`. Stats the file at most once a second.
+    pub(crate) fn is_stale(&mut self) -> bool {
+        if self.last_check.elapsed() >= Duration::from_secs(1) {
+            self.last_check = Instant::now();
+            if mtime(&self.path) != self.mtime {
+                self.unreported_since.get_or_insert_with(Instant::now);
+            }
+        }
+        self.unreported_since
+            .is_some_and(|since| since.elapsed() >= STALE_WATCH_AFTER)
+    }
+}
+
+/// Watches the modules the active file imports, natively or by polling
+/// their mtimes to match the watched files.
+pub(crate) struct ImportWatch {
+    kind: WatcherKind,
+    poll_interval: Duration,
+    // Each watched module with its mtime when last polled.
+    files: Vec<(PathBuf, Option<SystemTime>)>,
+    // Held while the files are watched; replaced with the watch set.
+    watchers: Vec<RecommendedWatcher>,
+    tx: Sender<notify::Result<notify::Event>>,
+    rx: Receiver<notify::Result<notify::Event>>,
+    last_poll: Instant,
+}
+
+impl ImportWatch {
+    pub(crate) fn new(kind: WatcherKind, poll_interval: Duration) -> Self {
+        let (tx, rx) = channel();
+        Self {
+            kind,
+            poll_interval,
+            files: Vec::new(),
+            watchers: Vec::new(),
+            tx,
+            rx,
+            last_poll: Instant::now(),
+        }
+    }
+
+    /// Watches `files` in place of the current set, if it differs.
+    pub(crate) fn set_files(&mut self, files: Vec<PathBuf>) {
+        if self.files.iter().map(|(path, _)| path).eq(files.iter()) {
+            return;
+        }
+        self.watchers = match self.kind {
+            WatcherKind::Notify => files
+                .iter()
+                .filter_map(|file| {
+                    start_notify_watcher(&file.to_string_lossy(), self.tx.clone()).ok()
+                })
+                .collect(),
+            WatcherKind::Polling => Vec::new(),
+        };
+        // Events from the previous set no longer apply.
+        self.rx.try_iter().for_each(drop);
+        self.files = files
+            .into_iter()
+            .map(|file| {
+                let modified = mtime(&file);
+                (file, modified)
+            })
+            .collect();
+    }
+
+    /// A watched module that changed since the last call, if any.
+    pub(crate) fn changed_file(&mut self) -> Option<PathBuf> {
+        let mut changed = None;
+        for event in self.rx.try_iter().flatten() {
+            if matches!(event.kind, EventKind::Modify(_)) {
+                changed = event.paths.into_iter().next();
+            }
+        }
+        if self.kind == WatcherKind::Polling && self.last_poll.elapsed() >= self.poll_interval {
+            self.last_poll = Instant::now();
+            for (file, modified) in &mut self.files {
+                let current = mtime(file);
+                if current != *modified {
+                    *modified = current;
+                    changed = Some(file.clone());
+                }
+            }
+        }
+        changed
+    }
+}